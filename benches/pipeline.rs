@@ -0,0 +1,55 @@
+//! Throughput of the GPU solver with a single step in flight, against steps pipelined through several sets of buffers.
+//! Run with `cargo bench --bench pipeline`, on every usable GPU. Skipped when none are available
+
+use std::time::{Duration, Instant};
+use webgpu_bip39_validator::{bitcoin, targets, Backend, Job, Solver};
+
+/// Four steps of 2^24 candidates, about one in sixteen with a valid checksum
+const STENCIL: &str = "_ _ {abandon,ability,able,about,above,absent,absorb,abstract,absurd,abuse,access,accident,account,accuse,achieve,acid} resist rocket skill elder jungle zoo circle circle return";
+
+/// Steps in flight, one is the baseline without pipelining
+const BUFFERS: [usize; 3] = [1, 2, 3];
+
+fn main() {
+	let solver = match pollster::block_on(Solver::new(Backend::Gpu)) {
+		Ok(solver) => solver,
+		Err(err) => {
+			eprintln!("Skipping pipeline benchmark: {}", err);
+			return;
+		}
+	};
+
+	// a single target, hits are rare so the stages dominate
+	let job = |buffers: usize| {
+		let address = targets::parse_address("1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA", bitcoin::Network::Bitcoin).unwrap();
+		let targets = targets::Targets::new([targets::Target::Bitcoin(address)], bitcoin::Network::Bitcoin).unwrap();
+		Job::builder(STENCIL.split(' ')).targets(targets).buffers(buffers).build().unwrap()
+	};
+
+	// compiles the pipelines and warms up the device
+	run(&solver, &job(1));
+
+	let mut baseline = None;
+	for buffers in BUFFERS {
+		let job = job(buffers);
+		let (start, end) = job.range();
+		let elapsed = run(&solver, &job);
+		let baseline = *baseline.get_or_insert(elapsed);
+
+		println!(
+			"buffers = {}: {} candidates in {:.2?}, {:.0} candidates/s, {:.2}x",
+			buffers,
+			end - start,
+			elapsed,
+			(end - start) as f64 / elapsed.as_secs_f64(),
+			baseline.as_secs_f64() / elapsed.as_secs_f64()
+		);
+	}
+}
+
+/// Time taken to check every candidate of the job
+fn run(solver: &Solver, job: &Job) -> Duration {
+	let started = Instant::now();
+	solver.run(job, |_| ()).unwrap();
+	started.elapsed()
+}
@@ -0,0 +1,74 @@
+use super::Error;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+/// Progress of a scan, saved after every processed step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+	/// identifies the job parameters the progress belongs to
+	pub fingerprint: String,
+	/// every candidate before this index has been checked against the targets
+	pub next: u64,
+	/// steps checked by each device since the scan started, for reporting. A resumed scan continues from `next`
+	pub devices: Vec<u64>,
+}
+
+impl Checkpoint {
+	pub fn load(path: &str) -> Result<Checkpoint, Error> {
+		let contents = std::fs::read_to_string(path).map_err(|err| Error::Checkpoint(format!("Unable to read checkpoint `{}`: {}", path, err)))?;
+		let field = |name: &str| {
+			let prefix = format!("{} = ", name);
+			contents
+				.lines()
+				.find_map(|l| l.strip_prefix(&prefix))
+				.ok_or_else(|| Error::Checkpoint(format!("Checkpoint `{}` is missing `{}`", path, name)))
+		};
+
+		// checkpoints of a single device have no per device progress
+		let devices = field("devices").unwrap_or_default();
+
+		Ok(Checkpoint {
+			fingerprint: field("fingerprint")?.to_string(),
+			next: field("next")?.parse().map_err(|err| Error::Checkpoint(format!("Invalid `next` in checkpoint `{}`: {}", path, err)))?,
+			devices: devices
+				.split_whitespace()
+				.map(|steps| steps.parse().map_err(|err| Error::Checkpoint(format!("Invalid `devices` in checkpoint `{}`: {}", path, err))))
+				.collect::<Result<_, _>>()?,
+		})
+	}
+
+	/// Writes to a temporary file first, so an interrupted save never corrupts the previous checkpoint
+	pub fn save(&self, path: &str) -> Result<(), Error> {
+		let temporary = format!("{}.tmp", path);
+		let devices = self.devices.iter().map(|steps| steps.to_string()).collect::<Vec<_>>().join(" ");
+		let contents = format!("fingerprint = {}\nnext = {}\ndevices = {}\n", self.fingerprint, self.next, devices);
+
+		std::fs::write(&temporary, contents).map_err(|err| Error::Checkpoint(format!("Unable to write checkpoint `{}`: {}", temporary, err)))?;
+		std::fs::rename(&temporary, path).map_err(|err| Error::Checkpoint(format!("Unable to replace checkpoint `{}`: {}", path, err)))
+	}
+}
+
+/// Hashes every parameter that changes which candidates are checked, or what they are checked against
+pub(crate) fn fingerprint(config: &super::Config, targets: &super::targets::Targets) -> Result<String, Error> {
+	let mut engine = sha256::Hash::engine();
+	let mut field = |name: &str, value: &[u8]| {
+		// length prefixed, so adjacent fields can't be confused
+		for bytes in [name.as_bytes(), value] {
+			engine.input(&(bytes.len() as u64).to_le_bytes());
+			engine.input(bytes);
+		}
+	};
+
+	let read = |path: &str| std::fs::read(path).map_err(|err| Error::config("Passphrases", format!("Unable to read `{}` for the job fingerprint: {}", path, err)));
+
+	field("stencil", config.stencil.join(" ").as_bytes());
+	field("language", format!("{:?}", config.language).as_bytes());
+	field("range", format!("{:?}", config.range).as_bytes());
+	field("passphrase", config.passphrase.as_deref().unwrap_or_default().as_bytes());
+	field("mask", config.mask.as_deref().unwrap_or_default().as_bytes());
+	field("passphrases", &config.passphrases.as_deref().map(read).transpose()?.unwrap_or_default());
+	field("targets", targets.digest().as_byte_array());
+	field("path", config.path.join(" ").as_bytes());
+	field("network", config.network().to_string().as_bytes());
+
+	Ok(sha256::Hash::from_engine(engine).to_string())
+}
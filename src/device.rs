@@ -1,34 +1,163 @@
-pub(crate) async fn init() -> (wgpu::Device, wgpu::Queue) {
-	// acquire instance
-	let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
-
-	// acquire adapter
-	let adapter = instance
-		.request_adapter(&wgpu::RequestAdapterOptions {
-			power_preference: wgpu::PowerPreference::HighPerformance,
-			..Default::default()
+use super::Error;
+
+/// Features the solver's shaders rely on
+pub const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::IMMEDIATES.union(wgpu::Features::SHADER_INT64);
+
+/// An adapter picked by its index among every adapter of the enabled backends, or by a case-insensitive part of its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelection {
+	Index(usize),
+	Name(String),
+}
+
+impl std::str::FromStr for AdapterSelection {
+	type Err = std::convert::Infallible;
+
+	fn from_str(selection: &str) -> Result<Self, Self::Err> {
+		Ok(match selection.parse() {
+			Ok(index) => AdapterSelection::Index(index),
+			Err(_) => AdapterSelection::Name(selection.to_string()),
 		})
-		.await
-		.unwrap();
+	}
+}
+
+/// Opens a device on every selected adapter of the backends. Every usable GPU is opened if none are selected,
+/// software adapters are only used if they are all there is. Selecting an adapter twice is a config error
+pub(crate) async fn init_all(backends: wgpu::Backends, selection: &[AdapterSelection]) -> Result<Vec<(wgpu::Device, wgpu::Queue)>, Error> {
+	let adapters = adapters(backends).await;
+	if adapters.is_empty() {
+		return Err(Error::Device(format!("No adapters were found on the backends {:?}", backends)));
+	}
+
+	let selected = match selection.is_empty() {
+		true => {
+			// an adapter exposed by several backends is opened once
+			let mut buses = std::collections::HashSet::new();
+			let usable = adapters
+				.iter()
+				.filter(|adapter| missing(adapter).is_empty())
+				.filter(|adapter| {
+					let bus = adapter.get_info().device_pci_bus_id;
+					bus.is_empty() || buses.insert(bus)
+				})
+				.collect::<Vec<_>>();
+
+			if usable.is_empty() {
+				let reasons = adapters.iter().map(unsupported).collect::<Vec<_>>();
+				return Err(Error::Device(format!("No adapter supports the required features: {}", reasons.join("; "))));
+			}
+
+			let hardware = usable.iter().copied().filter(|adapter| adapter.get_info().device_type != wgpu::DeviceType::Cpu).collect::<Vec<_>>();
+			match hardware.is_empty() {
+				true => usable,
+				false => hardware,
+			}
+		}
+		false => {
+			let selected = selection.iter().map(|selection| select(&adapters, selection)).collect::<Result<Vec<_>, _>>()?;
+
+			// two selections of the same adapter, or of one adapter exposed by several backends, would run steps twice on one GPU
+			for (idx, adapter) in selected.iter().enumerate() {
+				let info = adapter.get_info();
+				let duplicate = selected[..idx].iter().position(|other| {
+					let other_info = other.get_info();
+					std::ptr::eq(*adapter, *other) || (!info.device_pci_bus_id.is_empty() && info.device_pci_bus_id == other_info.device_pci_bus_id)
+				});
+
+				if let Some(other) = duplicate {
+					return Err(Error::config(
+						"Adapter",
+						format!("Selections {:?} and {:?} are the same adapter {}", selection[other], selection[idx], info.name),
+					));
+				}
+			}
+
+			selected
+		}
+	};
+
+	let mut devices = Vec::with_capacity(selected.len());
+	for adapter in selected {
+		let info = adapter.get_info();
+		log::info!("Using Adapter {}: {} ({:?})", devices.len(), info.name, info.backend);
 
+		devices.push(open(adapter).await?);
+	}
+
+	Ok(devices)
+}
+
+/// Every adapter of the backends, in the order they are enumerated. Selections index into this list
+pub(crate) async fn adapters(backends: wgpu::Backends) -> Vec<wgpu::Adapter> {
+	let mut descriptor = wgpu::InstanceDescriptor::new_without_display_handle_from_env();
+	descriptor.backends = backends;
+
+	let instance = wgpu::Instance::new(descriptor);
+	instance.enumerate_adapters(backends).await
+}
+
+/// Required features the adapter doesn't support
+fn missing(adapter: &wgpu::Adapter) -> wgpu::Features {
+	REQUIRED_FEATURES - adapter.features()
+}
+
+/// Names the adapter and the required features it lacks
+fn unsupported(adapter: &wgpu::Adapter) -> String {
+	let info = adapter.get_info();
+	let names = missing(adapter).iter_names().map(|(name, _)| name).collect::<Vec<_>>();
+	format!("{} ({:?}) lacks {}", info.name, info.backend, names.join(", "))
+}
+
+/// The adapter picked by a selection, which must support the required features. Names match the first usable adapter containing them
+fn select<'a>(adapters: &'a [wgpu::Adapter], selection: &AdapterSelection) -> Result<&'a wgpu::Adapter, Error> {
+	let adapter = match selection {
+		AdapterSelection::Index(index) => adapters
+			.get(*index)
+			.ok_or_else(|| Error::Device(format!("Adapter {} doesn't exist, {} adapters were found", index, adapters.len())))?,
+		AdapterSelection::Name(name) => {
+			let name = name.to_lowercase();
+			let named = adapters.iter().filter(|adapter| adapter.get_info().name.to_lowercase().contains(&name)).collect::<Vec<_>>();
+
+			named
+				.iter()
+				.find(|adapter| missing(adapter).is_empty())
+				.or(named.first())
+				.copied()
+				.ok_or_else(|| Error::Device(format!("No adapter's name contains `{}`", name)))?
+		}
+	};
+
+	match missing(adapter).is_empty() {
+		true => Ok(adapter),
+		false => Err(Error::Device(format!("Selected adapter {}", unsupported(adapter)))),
+	}
+}
+
+/// Acquires the device and queue of an adapter supporting the required features
+async fn open(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue), Error> {
 	// acquire device and queue
 	let device_options = wgpu::DeviceDescriptor {
 		label: Some("address_extractor"),
-		required_features: wgpu::Features::IMMEDIATES | wgpu::Features::SHADER_INT64,
+		required_features: REQUIRED_FEATURES,
 		required_limits: adapter.limits(),
 		..Default::default()
 	};
 
-	let (device, queue) = pollster::block_on(adapter.request_device(&device_options)).unwrap();
+	let (device, queue) = adapter
+		.request_device(&device_options)
+		.await
+		.map_err(|err| Error::Device(format!("Unable to open {}: {}", adapter.get_info().name, err)))?;
 
 	// init error handling
 	device.on_uncaptured_error(std::sync::Arc::new(|err| {
 		log::error!("[FATAL] Uncaptured error: {}", err);
 	}));
 
-	device.set_device_lost_callback(Box::new(|err, cb| {
-		panic!("Device lost: {:?}. Message: {}", err, cb);
+	// the steps in flight fail to be read back once the device is lost, and the solver fails with the error
+	let name = adapter.get_info().name;
+	device.set_device_lost_callback(Box::new(move |reason, message| {
+		log::error!("Device {} lost: {:?}. Message: {}", name, reason, message);
 	}));
 
-	(device, queue)
+	Ok((device, queue))
 }
@@ -0,0 +1,600 @@
+use super::*;
+use std::{
+	collections::{HashMap, HashSet},
+	io::{BufRead, BufReader, Read, Write},
+	net::{TcpListener, TcpStream},
+	str::FromStr,
+	sync::{Mutex, MutexGuard},
+	time::{Duration, Instant},
+};
+
+/// Steps handed out per lease, by default
+pub const DEFAULT_LEASE_STEPS: u64 = 16;
+
+/// A lease is handed to another worker once its worker has been silent this long, by default
+pub const DEFAULT_LEASE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long a worker waits before asking again while every step is leased. The coordinator keeps answering this long after the range is covered
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the coordinator sleeps while no connections are pending
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a request or response may take to arrive, a stalled connection doesn't hold up the coordinator
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Holds a job and hands out leases of its steps to workers over TCP, collecting their matches and coverage.
+///
+/// Every connection starts with `auth <token>`, the secret shared by the coordinator and its workers, followed by a single request line.
+/// Connections without the token are answered `unauthorized`. Lines aren't encrypted: the token keeps other hosts from reading the job,
+/// passphrase included, or reporting to it, but anyone watching the network sees both. Run it on a trusted network or through a tunnel.
+///
+/// Requests are answered by a single line:
+/// - `job`: the parameters of the job as `name = value` lines, ending with `end`
+/// - `lease <worker>`: `lease <id> <start> <end> <renew millis>`, `wait <millis>` while every step is leased, or `done` once the range is covered
+/// - `renew <id>`, `progress <id> <next> <derived>` and `done <id>`: `ok`, or `lost` once the lease expired and was handed out again
+/// - `match <id> <candidate> <path>`: `ok` once the match is confirmed against the targets, `rejected` otherwise
+pub struct Coordinator {
+	job: Job,
+	token: Option<String>,
+	lease_steps: u64,
+	lease_timeout: Duration,
+	interrupt: Interrupt,
+}
+
+/// Steps of the range leased to a worker
+struct Lease {
+	/// index of the worker, in the order workers first asked for a lease
+	worker: usize,
+	/// every candidate of the lease before `next` has been checked
+	next: u64,
+	end: u64,
+	renewed: Instant,
+}
+
+/// Coverage of the job's range, and the leases handed out
+struct Leases {
+	/// start of the part of the range never leased
+	cursor: u64,
+	/// remainders of expired leases, leased again before the cursor moves on
+	free: Vec<(u64, u64)>,
+	active: HashMap<u64, Lease>,
+	ids: u64,
+	workers: Vec<String>,
+	/// candidates checked by every lease
+	covered: u64,
+	/// matches as (candidate, path), a candidate checked twice is reported once
+	matches: HashSet<(u64, String)>,
+}
+
+impl Leases {
+	/// Every candidate before this index has been checked
+	fn next(&self, end: u64) -> u64 {
+		let pending = self.free.iter().map(|range| range.0).chain(self.active.values().map(|lease| lease.next));
+		pending.chain([self.cursor]).min().unwrap_or(end).min(end)
+	}
+
+	/// Index of a worker by its name
+	fn worker(&mut self, name: &str) -> usize {
+		match self.workers.iter().position(|worker| worker == name) {
+			Some(index) => index,
+			None => {
+				log::info!(target: "distributed::coordinator", "Worker {} joined: {}", self.workers.len(), name);
+				self.workers.push(name.to_string());
+				self.workers.len() - 1
+			}
+		}
+	}
+
+	/// Hands out the unchecked remainder of leases whose workers have been silent for `timeout`
+	fn expire(&mut self, timeout: Duration) {
+		let expired = self.active.iter().filter(|(_, lease)| lease.renewed.elapsed() >= timeout).map(|(&id, _)| id).collect::<Vec<_>>();
+
+		for id in expired {
+			let lease = self.active.remove(&id).unwrap();
+			log::warn!(target: "distributed::coordinator", "Lease {} of worker {} expired, [{}, {}) will be leased again", id, self.workers[lease.worker], lease.next, lease.end);
+
+			if lease.next < lease.end {
+				self.free.push((lease.next, lease.end));
+			}
+		}
+	}
+}
+
+impl Coordinator {
+	pub fn new(job: Job) -> Coordinator {
+		Coordinator {
+			job,
+			token: None,
+			lease_steps: DEFAULT_LEASE_STEPS,
+			lease_timeout: DEFAULT_LEASE_TIMEOUT,
+			interrupt: Interrupt::default(),
+		}
+	}
+
+	/// Secret every worker must present, required before serving
+	pub fn token<S: Into<String>>(mut self, token: S) -> Coordinator {
+		self.token = Some(token.into());
+		self
+	}
+
+	/// Steps handed out per lease. Default is 16
+	pub fn lease_steps(mut self, steps: u64) -> Coordinator {
+		self.lease_steps = steps.max(1);
+		self
+	}
+
+	/// A lease is handed to another worker once its worker has been silent this long. Default is 2 minutes
+	pub fn lease_timeout(mut self, timeout: Duration) -> Coordinator {
+		self.lease_timeout = timeout;
+		self
+	}
+
+	/// Stops the coordinator from leasing more steps, it returns once the leases already handed out are checked or expire
+	pub fn interrupt(&self) -> Interrupt {
+		self.interrupt.clone()
+	}
+
+	pub fn job(&self) -> &Job {
+		&self.job
+	}
+
+	/// Serves workers until the job's range is covered, calling `callback` with each confirmed match and after each step checked by a worker.
+	/// Progress is reported by worker index, in the order workers first asked for a lease. Each connection is answered on its own thread, `callback` is called from them one at a time.
+	/// Once interrupted, fails with the part of the range that was covered after the active leases are checked
+	pub fn serve<F: FnMut(Event) + Send>(&self, listener: TcpListener, callback: F) -> Result<(), Error> {
+		let end = self.job.config.range.1;
+		let network_error = |err: std::io::Error| Error::Network(format!("Unable to accept workers: {}", err));
+
+		let Some(token) = self.token.as_deref().filter(|token| !token.is_empty()) else {
+			return Err(Error::config("Token", "Workers must authenticate with a shared token"));
+		};
+
+		listener.set_nonblocking(true).map_err(network_error)?;
+		let address = listener.local_addr().map_err(network_error)?;
+		log::info!(target: "distributed::coordinator", "Serving {} steps on {}", self.job.steps(), address);
+
+		if !address.ip().is_loopback() {
+			log::warn!(
+				target: "distributed::coordinator",
+				"Listening on {}, reachable from other hosts. Requests aren't encrypted, the job and its passphrase are readable by anyone watching the network",
+				address
+			);
+		}
+
+		let resolver = scan::Resolver::new(&self.job)?;
+		let description = self.describe()?;
+		let leases = Leases {
+			cursor: self.job.config.range.0,
+			free: Vec::new(),
+			active: HashMap::new(),
+			ids: 0,
+			workers: Vec::new(),
+			covered: 0,
+			matches: HashSet::new(),
+		};
+
+		// connections are answered on their own threads, a stalled worker only holds up its own request
+		let state = Mutex::new((leases, callback));
+
+		std::thread::scope(|scope| {
+			// waiting workers are told the job is done before returning
+			let mut covered = None;
+
+			loop {
+				{
+					let leases = &mut lock(&state).0;
+					leases.expire(self.lease_timeout);
+
+					if leases.next(end) == end && covered.get_or_insert_with(Instant::now).elapsed() >= POLL_INTERVAL * 2 {
+						return Ok(());
+					}
+
+					if self.interrupt.is_interrupted() && leases.next(end) < end && leases.active.is_empty() {
+						return Err(Error::interrupted(self.job.config.range.0, leases.next(end)));
+					}
+				}
+
+				match listener.accept() {
+					Ok((stream, address)) => {
+						let (state, resolver, description) = (&state, &resolver, description.as_str());
+
+						scope.spawn(move || {
+							if let Err(err) = self.handle(stream, token, state, resolver, description) {
+								log::warn!(target: "distributed::coordinator", "Request from {} failed: {}", address, err);
+							}
+						});
+					}
+					Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_INTERVAL),
+					Err(err) => return Err(network_error(err)),
+				}
+			}
+		})
+	}
+
+	/// Answers the single request of a connection, once it presented the token. The request is read and the response written without holding the leases
+	fn handle<F: FnMut(Event)>(&self, stream: TcpStream, token: &str, state: &Mutex<(Leases, F)>, resolver: &scan::Resolver, description: &str) -> std::io::Result<()> {
+		stream.set_nonblocking(false)?;
+		stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+		stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+		let mut reader = BufReader::new(&stream);
+		let mut auth = String::new();
+		reader.read_line(&mut auth)?;
+
+		let authorized = auth.trim_end().strip_prefix("auth ").is_some_and(|presented| same(presented.as_bytes(), token.as_bytes()));
+		if !authorized {
+			(&stream).write_all(b"unauthorized\n")?;
+			return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Connection didn't present the token"));
+		}
+
+		let mut request = String::new();
+		reader.read_line(&mut request)?;
+
+		let response = {
+			let (leases, callback) = &mut *lock(state);
+			self.respond(request.trim(), leases, resolver, description, callback)
+		};
+
+		(&stream).write_all(response.as_bytes())
+	}
+
+	fn respond<F: FnMut(Event)>(&self, request: &str, leases: &mut Leases, resolver: &scan::Resolver, description: &str, callback: &mut F) -> String {
+		let (start, end) = self.job.config.range;
+		let step_size = self.job.step_size();
+
+		let words = request.split_whitespace().collect::<Vec<_>>();
+		let number = |word: &str| word.parse::<u64>().ok();
+
+		match words.as_slice() {
+			["job"] => description.to_string(),
+			["lease", _] if self.interrupt.is_interrupted() => format!("wait {}\n", POLL_INTERVAL.as_millis()),
+			["lease", worker] => {
+				let worker = leases.worker(worker);
+
+				// remainders of expired leases first, then the rest of the range
+				let range = leases.free.pop().or_else(|| {
+					(leases.cursor < end).then(|| {
+						let range = (leases.cursor, leases.cursor.saturating_add(self.lease_steps.saturating_mul(step_size)).min(end));
+						leases.cursor = range.1;
+						range
+					})
+				});
+
+				match range {
+					Some((next, end)) => {
+						leases.ids += 1;
+						leases.active.insert(
+							leases.ids,
+							Lease {
+								worker,
+								next,
+								end,
+								renewed: Instant::now(),
+							},
+						);
+
+						log::info!(target: "distributed::coordinator", "Leased [{}, {}) to worker {} as lease {}", next, end, leases.workers[worker], leases.ids);
+						format!("lease {} {} {} {}\n", leases.ids, next, end, (self.lease_timeout / 4).as_millis())
+					}
+					None if leases.next(end) == end => "done\n".to_string(),
+					None => format!("wait {}\n", POLL_INTERVAL.as_millis()),
+				}
+			}
+			["renew", id] => match number(id).and_then(|id| leases.active.get_mut(&id)) {
+				Some(lease) => {
+					lease.renewed = Instant::now();
+					"ok\n".to_string()
+				}
+				None => "lost\n".to_string(),
+			},
+			["progress", id, next, derived] => {
+				let (Some(id), Some(next), Ok(derived)) = (number(id), number(next), derived.parse()) else {
+					return format!("error Malformed request: {}\n", request);
+				};
+
+				let Some(lease) = leases.active.get_mut(&id) else {
+					return "lost\n".to_string();
+				};
+
+				// coverage never moves backwards, or past the lease
+				let next = next.clamp(lease.next, lease.end);
+				let worker = lease.worker;
+				let filtered = next - lease.next;
+
+				leases.covered += filtered;
+				lease.next = next;
+				lease.renewed = Instant::now();
+
+				callback(Event::Progress(Progress {
+					device: worker,
+					next: leases.next(end),
+					completed: leases.covered.div_ceil(step_size),
+					steps: self.job.steps(),
+					filtered,
+					derived,
+				}));
+
+				"ok\n".to_string()
+			}
+			["done", id] => match number(id).and_then(|id| leases.active.remove(&id)) {
+				Some(lease) => {
+					// steps the worker never reported are leased again
+					if lease.next < lease.end {
+						log::warn!(target: "distributed::coordinator", "Worker {} finished a lease without checking [{}, {})", leases.workers[lease.worker], lease.next, lease.end);
+						leases.free.push((lease.next, lease.end));
+					}
+
+					"ok\n".to_string()
+				}
+				None => "lost\n".to_string(),
+			},
+			["match", _, candidate, path] => {
+				let (Some(candidate), Ok(path)) = (number(candidate), bitcoin::bip32::DerivationPath::from_str(path)) else {
+					return format!("error Malformed request: {}\n", request);
+				};
+
+				// only candidates of the job at its paths, confirmed on the CPU
+				let found = match (start..end).contains(&candidate) && self.job.paths.iter().any(|p| p == path) {
+					true => resolver.resolve(candidate, &path),
+					false => Ok(None),
+				};
+
+				match found {
+					Ok(Some(found)) => {
+						if leases.matches.insert((candidate, path.to_string())) {
+							callback(Event::Match(Box::new(found)));
+						}
+
+						"ok\n".to_string()
+					}
+					Ok(None) => "rejected\n".to_string(),
+					Err(err) => format!("error {}\n", err),
+				}
+			}
+			_ => format!("error Unsupported request: {}\n", request),
+		}
+	}
+
+	/// Parameters workers rebuild the job from. Free-form values are hex encoded to fit on a line, which doesn't hide them
+	fn describe(&self) -> Result<String, Error> {
+		let config = &self.job.config;
+		let mut lines = vec![
+			format!("fingerprint = {}", self.job.fingerprint()?),
+			format!("stencil = {}", config.stencil.join(" ")),
+			format!("language = {}", self.job.language()),
+			format!("range = {} {}", config.range.0, config.range.1),
+			format!("network = {}", config.network()),
+		];
+
+		let optional = [("passphrase", &config.passphrase), ("mask", &config.mask), ("passphrases", &config.passphrases)];
+		lines.extend(optional.into_iter().filter_map(|(name, value)| value.as_ref().map(|value| format!("{} = {}", name, hex::encode(value)))));
+		lines.extend(config.path.iter().map(|template| format!("path = {}", hex::encode(template))));
+		lines.push("end\n".to_string());
+
+		Ok(lines.join("\n"))
+	}
+}
+
+/// Checks leases of a coordinator's job, reporting matches and coverage back to the coordinator
+pub struct Worker {
+	address: String,
+	name: String,
+	token: Option<String>,
+	fingerprint: Option<String>,
+}
+
+impl Worker {
+	/// Worker of the coordinator at `address`, identified by `name` in the coordinator's progress
+	pub fn new<A: Into<String>, N: Into<String>>(address: A, name: N) -> Worker {
+		Worker {
+			address: address.into(),
+			name: name.into(),
+			token: None,
+			fingerprint: None,
+		}
+	}
+
+	/// Secret shared with the coordinator, required by every request
+	pub fn token<S: Into<String>>(mut self, token: S) -> Worker {
+		self.token = Some(token.into());
+		self
+	}
+
+	/// The coordinator's job, on the worker's own copy of its targets read by `targets` for the job's network.
+	/// Passphrase files are read from the same path as the coordinator's. Options that don't change the candidates, such as `dispatch`, may be set before building
+	pub fn job<F: FnOnce(bitcoin::Network) -> Result<targets::Targets, Error>>(&mut self, targets: F) -> Result<JobBuilder, Error> {
+		let description = self.request("job")?;
+		let malformed = |line: &str| Error::Network(format!("Malformed job description from `{}`: {}", self.address, line));
+		let decode = |value: &str| hex::decode(value).ok().and_then(|bytes| String::from_utf8(bytes).ok()).ok_or_else(|| malformed(value));
+
+		let mut fields = HashMap::new();
+		let mut templates = Vec::new();
+
+		for line in description.lines().take_while(|line| *line != "end") {
+			let (name, value) = line.split_once(" = ").ok_or_else(|| malformed(line))?;
+			match name {
+				"path" => templates.push(decode(value)?),
+				"passphrase" | "mask" | "passphrases" => drop(fields.insert(name, decode(value)?)),
+				_ => drop(fields.insert(name, value.to_string())),
+			}
+		}
+
+		let field = |name: &str| fields.get(name).map(String::as_str).ok_or_else(|| malformed(&format!("missing `{}`", name)));
+		let network = parse_network(field("network")?).map_err(|err| malformed(&err))?;
+		let language = parse_language(field("language")?).map_err(|err| malformed(&err))?;
+		let range = field("range")?
+			.split_once(' ')
+			.and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)))
+			.ok_or_else(|| malformed("range"))?;
+
+		let mut builder = Job::builder(field("stencil")?.split(' ')).language(language).range(range.0, range.1).targets(targets(network)?);
+		if let Ok(passphrase) = field("passphrase") {
+			builder = builder.passphrase(passphrase);
+		}
+		if let Ok(mask) = field("mask") {
+			builder = builder.mask(mask);
+		}
+		if let Ok(passphrases) = field("passphrases") {
+			builder = builder.passphrases(passphrases);
+		}
+		for template in templates {
+			builder = builder.path(template);
+		}
+
+		self.fingerprint = Some(field("fingerprint")?.to_string());
+		Ok(builder)
+	}
+
+	/// Checks leases of the job until the coordinator's range is covered, calling `callback` with the matches and progress of every lease.
+	/// The job must be built from `job`, on the same targets and passphrases as the coordinator's.
+	/// Once the solver is interrupted, the lease being checked is handed back and the worker fails with every range it covered
+	pub fn run<F: FnMut(Event)>(&self, solver: &Solver, job: &Job, mut callback: F) -> Result<(), Error> {
+		if self.fingerprint.as_deref() != Some(job.fingerprint()?.as_str()) {
+			return Err(Error::config("Job", "Differs from the coordinator's job, the targets or passphrases don't match"));
+		}
+
+		// leases checked by this worker, other workers check the gaps between them
+		let mut covered = Vec::new();
+
+		loop {
+			if solver.interrupt().is_interrupted() {
+				return Err(Error::Interrupted { covered });
+			}
+
+			let response = self.request(&format!("lease {}", self.name))?;
+			let number = |word: &str| word.parse::<u64>().map_err(|_| Error::Network(format!("Unexpected response from `{}`: {}", self.address, response.trim())));
+
+			match response.split_whitespace().collect::<Vec<_>>().as_slice() {
+				["done"] => return Ok(()),
+				["wait", millis] => std::thread::sleep(Duration::from_millis(number(millis)?)),
+				["lease", id, start, end, renew] => {
+					let lease = job.slice(number(start)?, number(end)?);
+
+					match self.lease(solver, &lease, number(id)?, Duration::from_millis(number(renew)?), &mut callback) {
+						Ok(()) => cover(&mut covered, lease.range()),
+						Err(Error::Interrupted { covered: partial }) => {
+							partial.into_iter().for_each(|range| cover(&mut covered, range));
+							return Err(Error::Interrupted { covered });
+						}
+						Err(err) => return Err(err),
+					}
+				}
+				_ => return Err(Error::Network(format!("Unexpected response from `{}`: {}", self.address, response.trim()))),
+			}
+		}
+	}
+
+	/// Checks every step of a lease, renewing it every `renew` while steps are slow to complete
+	fn lease<F: FnMut(Event)>(&self, solver: &Solver, job: &Job, id: u64, renew: Duration, callback: &mut F) -> Result<(), Error> {
+		log::info!(target: "distributed::worker", "Checking lease {}: [{}, {})", id, job.range().0, job.range().1);
+
+		// reports to the coordinator can't fail the solver mid step, the first failure is returned afterwards
+		let mut failed = None;
+
+		let result = std::thread::scope(|scope| {
+			let (stop, stopped) = flume::bounded::<()>(0);
+			scope.spawn(move || {
+				while let Err(flume::RecvTimeoutError::Timeout) = stopped.recv_timeout(renew) {
+					if let Err(err) = self.request(&format!("renew {}", id)) {
+						log::warn!(target: "distributed::worker", "Unable to renew lease {}: {}", id, err);
+					}
+				}
+			});
+
+			let result = solver.run(job, |event| {
+				let report = match &event {
+					Event::Match(found) => format!("match {} {} m/{}", id, found.candidate, found.path),
+					Event::Progress(progress) => format!("progress {} {} {}", id, progress.next, progress.derived),
+				};
+
+				match self.request(&report) {
+					Ok(response) => match response.trim() {
+						"ok" => (),
+						"lost" => log::warn!(target: "distributed::worker", "Lease {} was handed to another worker", id),
+						response => log::warn!(target: "distributed::worker", "Coordinator refused `{}`: {}", report, response),
+					},
+					Err(err) => drop(failed.get_or_insert(err)),
+				}
+
+				callback(event);
+			});
+
+			drop(stop);
+			result
+		});
+
+		// an interrupted lease is handed back, the coordinator leases the steps it didn't cover again
+		let interrupted = match result {
+			Err(Error::Interrupted { covered }) => Some(covered),
+			result => result.map(|_| None)?,
+		};
+
+		if let Some(err) = failed {
+			return Err(err);
+		}
+
+		match self.request(&format!("done {}", id))?.trim() {
+			"ok" => (),
+			"lost" => log::warn!(target: "distributed::worker", "Lease {} was handed to another worker before it was checked", id),
+			response => return Err(Error::Network(format!("Unexpected response from `{}`: {}", self.address, response))),
+		}
+
+		match interrupted {
+			Some(covered) => Err(Error::Interrupted { covered }),
+			None => Ok(()),
+		}
+	}
+
+	/// Sends a single request to the coordinator after the token, returning its response
+	fn request(&self, request: &str) -> Result<String, Error> {
+		let network_error = |err: std::io::Error| Error::Network(format!("`{}`: {}", self.address, err));
+		let Some(token) = self.token.as_deref().filter(|token| !token.is_empty()) else {
+			return Err(Error::config("Token", "The coordinator requires a shared token"));
+		};
+
+		let mut stream = TcpStream::connect(&self.address).map_err(network_error)?;
+		stream.set_read_timeout(Some(REQUEST_TIMEOUT)).map_err(network_error)?;
+		stream.set_write_timeout(Some(REQUEST_TIMEOUT)).map_err(network_error)?;
+		stream.write_all(format!("auth {}\n{}\n", token, request).as_bytes()).map_err(network_error)?;
+
+		// the coordinator closes the connection once it has responded
+		let mut response = String::new();
+		stream.read_to_string(&mut response).map_err(network_error)?;
+
+		match response.trim() {
+			"unauthorized" => Err(Error::Network(format!("`{}` rejected the token", self.address))),
+			_ => Ok(response),
+		}
+	}
+}
+
+/// Compares a presented token in constant time, so its bytes can't be guessed one at a time from response times
+fn same(presented: &[u8], token: &[u8]) -> bool {
+	presented.len() == token.len() && presented.iter().zip(token).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Locks the coordinator's state, a connection thread that panicked holding it doesn't stop the others
+fn lock<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+	state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Adds a range to sorted, disjoint ranges, merging it with the ranges it overlaps or touches
+pub(crate) fn cover(covered: &mut Vec<(u64, u64)>, range: (u64, u64)) {
+	if range.0 >= range.1 {
+		return;
+	}
+
+	covered.push(range);
+	covered.sort_unstable();
+
+	let mut merged: Vec<(u64, u64)> = Vec::with_capacity(covered.len());
+	for &(start, end) in covered.iter() {
+		match merged.last_mut() {
+			Some(last) if start <= last.1 => last.1 = last.1.max(end),
+			_ => merged.push((start, end)),
+		}
+	}
+
+	*covered = merged;
+}
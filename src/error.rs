@@ -0,0 +1,72 @@
+/// Failures of building or running a job
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	/// a parameter of the job is invalid, such as its range, passphrase or derivation paths
+	#[error("Invalid {field}: {reason}")]
+	Config { field: &'static str, reason: String },
+	/// the stencil has no valid words, or isn't a complete mnemonic during passphrase recovery
+	#[error("Invalid Stencil: {0}")]
+	Stencil(String),
+	/// the address file or index can't be read or written, or holds an unsupported target
+	#[error("Invalid Addresses: {0}")]
+	Addresses(String),
+	/// the checkpoint can't be read or written, or belongs to a different job
+	#[error("Invalid Checkpoint: {0}")]
+	Checkpoint(String),
+	/// no adapter or device with the required features and limits, or a device failed while solving
+	#[error("Device Unavailable: {0}")]
+	Device(String),
+	/// a buffer couldn't be read back from the device or overflowed, or its results couldn't be confirmed
+	#[error("Unable to map GPU buffer: {0}")]
+	Mapping(String),
+	/// matches couldn't be written to the found file
+	#[error("Unable to write matches: {0}")]
+	Output(String),
+	/// the coordinator or a worker of a distributed job can't be reached, or broke the protocol
+	#[error("Network Failure: {0}")]
+	Network(String),
+	/// the job was interrupted before its range was covered, every candidate of the `covered` ranges was checked
+	#[error("Interrupted, {}", checked(covered))]
+	Interrupted { covered: Vec<(u64, u64)> },
+}
+
+impl Error {
+	pub(crate) fn config<S: Into<String>>(field: &'static str, reason: S) -> Error {
+		Error::Config { field, reason: reason.into() }
+	}
+
+	/// Interrupted after every candidate in [start, next) was checked
+	pub(crate) fn interrupted(start: u64, next: u64) -> Error {
+		let covered = match start < next {
+			true => vec![(start, next)],
+			false => Vec::new(),
+		};
+
+		Error::Interrupted { covered }
+	}
+
+	/// Exit code of the command line when failing with this error
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			Error::Config { .. } | Error::Stencil(_) => 2,
+			Error::Addresses(_) => 3,
+			Error::Checkpoint(_) => 4,
+			Error::Device(_) => 5,
+			Error::Mapping(_) => 6,
+			Error::Output(_) => 7,
+			Error::Network(_) => 8,
+			Error::Interrupted { .. } => 130,
+		}
+	}
+}
+
+/// Describes the ranges whose candidates were checked
+fn checked(covered: &[(u64, u64)]) -> String {
+	match covered.is_empty() {
+		true => "no candidates were checked".to_string(),
+		false => format!(
+			"candidates in {} were checked",
+			covered.iter().map(|range| format!("[{}, {})", range.0, range.1)).collect::<Vec<_>>().join(", ")
+		),
+	}
+}
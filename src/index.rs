@@ -0,0 +1,178 @@
+use super::{targets, Error};
+use std::io::{BufRead, Read, Write};
+
+/// Lines read between progress updates
+const PROGRESS_INTERVAL: usize = 1 << 20;
+
+/// Entries sorted in memory at once, about 88 MB. Larger inputs are sorted in chunks written next to the index, then merged into it
+const CHUNK_ENTRIES: usize = 1 << 22;
+
+type Entry = [u8; targets::Targets::ENTRY_SIZE];
+
+/// Builds an index of targets from a file of addresses, such as a dump of the UTXO set, one per line or in any column of a CSV file. Files ending in `.gz` are decompressed.
+/// Malformed lines and unsupported address types are skipped, then the sorted index is written to `output` and mapped back
+pub fn build(input: &str, output: &str, network: bitcoin::Network) -> Result<targets::Targets, Error> {
+	build_in_chunks(input, output, network, CHUNK_ENTRIES)
+}
+
+/// External sort of the targets of `input`: chunks of `chunk_entries` are sorted and written to temporary files, which are merged into the index
+pub(crate) fn build_in_chunks(input: &str, output: &str, network: bitcoin::Network, chunk_entries: usize) -> Result<targets::Targets, Error> {
+	let file = std::fs::File::open(input).map_err(|err| Error::Addresses(format!("Unable to open `{}`: {}", input, err)))?;
+
+	let reader: Box<dyn Read> = match input.ends_with(".gz") {
+		true => Box::new(flate2::read::MultiGzDecoder::new(file)),
+		false => Box::new(file),
+	};
+
+	// chunk files are removed once merged, or when building fails
+	let mut chunks = Chunks(Vec::new());
+	let mut chunk = Vec::with_capacity(chunk_entries.min(CHUNK_ENTRIES));
+	let mut lines = 0;
+	let mut skipped = 0;
+
+	for line in std::io::BufReader::new(reader).lines() {
+		lines += 1;
+		if lines % PROGRESS_INTERVAL == 0 {
+			log::info!(target: "index", "Lines Read = {}, Skipped = {}, Chunks = {}", lines, skipped, chunks.0.len());
+		}
+
+		let line = line.map_err(|err| Error::Addresses(format!("Unable to read line {} of `{}`: {}", lines, input, err)))?;
+
+		// labels aren't kept, an index only identifies targets
+		match parse_line(&line, network) {
+			Some(Ok((target, _))) => chunk.push(targets::Targets::entry(&target).map_err(Error::Addresses)?),
+			Some(Err(err)) => {
+				log::debug!(target: "index", "Skipping line {}: {}", lines, err);
+				skipped += 1;
+			}
+			None => {}
+		}
+
+		if chunk.len() == chunk_entries {
+			let path = write_chunk(output, chunks.0.len(), &mut chunk)?;
+			chunks.0.push(path);
+		}
+	}
+
+	// the last chunk is merged straight from memory
+	chunk.sort_unstable();
+	chunk.dedup();
+	let count = targets::Targets::write_index(output, network, merge(&chunks.0, chunk)?)?;
+
+	log::info!(target: "index", "Lines Read = {}, Skipped = {}, Targets = {}, Chunks = {}", lines, skipped, count, chunks.0.len() + 1);
+	drop(chunks);
+
+	let targets = targets::Targets::load(output, network)?;
+	log::info!(target: "index", "Index written to `{}`, Kinds = {:?}", output, targets.kinds());
+	Ok(targets)
+}
+
+/// Paths of the sorted chunks written so far, removed once dropped
+struct Chunks(Vec<String>);
+
+impl Drop for Chunks {
+	fn drop(&mut self) {
+		for path in self.0.iter() {
+			let _ = std::fs::remove_file(path);
+		}
+	}
+}
+
+/// Sorts a chunk of entries into a temporary file next to the index, emptying it
+fn write_chunk(output: &str, index: usize, chunk: &mut Vec<Entry>) -> Result<String, Error> {
+	chunk.sort_unstable();
+	chunk.dedup();
+
+	let path = format!("{}.chunk{}", output, index);
+	log::debug!(target: "index", "Writing {} Entries to `{}`", chunk.len(), path);
+
+	let write = || -> std::io::Result<()> {
+		let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+		file.write_all(chunk.as_flattened())?;
+		file.flush()
+	};
+
+	write().map_err(|err| Error::Addresses(format!("Unable to write `{}`: {}", path, err)))?;
+	chunk.clear();
+	Ok(path)
+}
+
+/// Merges the sorted chunk files and the sorted entries held in memory, without duplicates
+fn merge(chunks: &[String], last: Vec<Entry>) -> Result<impl Iterator<Item = std::io::Result<Entry>>, Error> {
+	use std::cmp::Reverse;
+
+	let mut sources = Vec::<Box<dyn Iterator<Item = std::io::Result<Entry>>>>::new();
+	for path in chunks {
+		let file = std::fs::File::open(path).map_err(|err| Error::Addresses(format!("Unable to read `{}`: {}", path, err)))?;
+		let mut reader = std::io::BufReader::new(file);
+
+		sources.push(Box::new(std::iter::from_fn(move || {
+			let mut entry = [0; targets::Targets::ENTRY_SIZE];
+			match reader.read_exact(&mut entry) {
+				Ok(()) => Some(Ok(entry)),
+				Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+				Err(err) => Some(Err(err)),
+			}
+		})));
+	}
+
+	sources.push(Box::new(last.into_iter().map(Ok)));
+
+	// smallest head of every source, an error is returned as soon as a source fails
+	let mut heads = std::collections::BinaryHeap::new();
+	let mut failed = None;
+	for (idx, source) in sources.iter_mut().enumerate() {
+		match source.next() {
+			Some(Ok(entry)) => heads.push(Reverse((entry, idx))),
+			Some(Err(err)) => failed = failed.or(Some(err)),
+			None => {}
+		}
+	}
+
+	let mut previous = None;
+	Ok(std::iter::from_fn(move || loop {
+		if let Some(err) = failed.take() {
+			return Some(Err(err));
+		}
+
+		let Reverse((entry, idx)) = heads.pop()?;
+		match sources[idx].next() {
+			Some(Ok(next)) => heads.push(Reverse((next, idx))),
+			Some(Err(err)) => failed = Some(err),
+			None => {}
+		}
+
+		// chunks are deduplicated, but may share entries with each other
+		if previous.replace(entry) != Some(entry) {
+			return Some(Ok(entry));
+		}
+	}))
+}
+
+/// Parses the first field of a line holding an address of a supported type, and the field following it as the label of the address.
+/// Fields are separated by commas, semicolons or tabs, and may be quoted. Blank lines are `None`
+pub fn parse_line(line: &str, network: bitcoin::Network) -> Option<Result<(targets::Target, Option<String>), String>> {
+	let line = line.trim();
+	if line.is_empty() {
+		return None;
+	}
+
+	let mut fields = line.split([',', ';', '\t']).map(|field| field.trim().trim_matches('"')).filter(|field| !field.is_empty());
+	let mut error = None;
+
+	while let Some(field) = fields.next() {
+		let target = match field.starts_with("0x") {
+			true => targets::parse_evm_address(field).map(targets::Target::Evm),
+			false => targets::parse_address(field, network).map(targets::Target::Bitcoin),
+		};
+
+		match target.and_then(|target| targets::AddressKind::of(&target).map(|_| target)) {
+			Ok(target) => return Some(Ok((target, fields.next().map(str::to_string)))),
+			Err(err) => {
+				error.get_or_insert(err);
+			}
+		}
+	}
+
+	Some(Err(error.unwrap_or_else(|| format!("No address in line: {}", line))))
+}
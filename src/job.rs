@@ -0,0 +1,164 @@
+use super::*;
+use std::sync::Arc;
+
+/// A verified scan: the candidates of a stencil or of its passphrases, checked against the targets at every derivation path
+#[derive(Clone)]
+pub struct Job {
+	pub(crate) config: Config,
+	pub(crate) targets: Arc<targets::Targets>,
+	pub(crate) paths: paths::Paths,
+}
+
+impl Job {
+	/// Starts a job from the words of a stencil. Unknown words are marked with `_`, partially known words with a pattern such as `ca*` or `?oo?`,
+	/// or a set such as `{cat,car,cable}`. Words may also be passed as a single sentence
+	pub fn builder<I: IntoIterator<Item = S>, S: AsRef<str>>(stencil: I) -> JobBuilder {
+		JobBuilder {
+			config: Config {
+				stencil: stencil.into_iter().map(|word| word.as_ref().to_string()).collect(),
+				range: (0, u64::MAX),
+				..Default::default()
+			},
+			targets: None,
+		}
+	}
+
+	/// Candidates in the range [start, end) are checked, bound to the problem space of the stencil or passphrases
+	pub fn range(&self) -> (u64, u64) {
+		self.config.range
+	}
+
+	pub fn language(&self) -> bip39::Language {
+		self.config.language.expect("Language should be selected when the job is built")
+	}
+
+	pub fn targets(&self) -> &targets::Targets {
+		&self.targets
+	}
+
+	pub fn paths(&self) -> &paths::Paths {
+		&self.paths
+	}
+
+	/// Number of candidates processed per step, progress is reported after every step
+	pub fn step_size(&self) -> u64 {
+		solver::step_size(&self.config) as u64
+	}
+
+	/// Number of steps covering the range
+	pub fn steps(&self) -> u64 {
+		(self.config.range.1 - self.config.range.0).div_ceil(self.step_size())
+	}
+
+	/// Identifies the parameters of the job, checkpoints of a job are only valid for jobs of the same fingerprint
+	pub fn fingerprint(&self) -> Result<String, Error> {
+		checkpoint::fingerprint(&self.config, &self.targets)
+	}
+
+	/// The candidates of the job in [start, end), a part of its range
+	pub(crate) fn slice(&self, start: u64, end: u64) -> Job {
+		let mut job = self.clone();
+		job.config.range = (start.max(self.config.range.0), end.min(self.config.range.1));
+		job
+	}
+
+	/// The rest of the job after a checkpoint saved while scanning it, `None` if the checkpoint has completed the job
+	pub fn resume(&self, checkpoint: &checkpoint::Checkpoint) -> Result<Option<Job>, Error> {
+		if checkpoint.fingerprint != self.fingerprint()? {
+			return Err(Error::Checkpoint("Checkpoint belongs to a scan with a different stencil, range, passphrases, paths or targets".to_string()));
+		}
+
+		if checkpoint.next >= self.config.range.1 {
+			return Ok(None);
+		}
+
+		let mut job = self.clone();
+		job.config.range.0 = job.config.range.0.max(checkpoint.next);
+		Ok(Some(job))
+	}
+}
+
+/// Parameters of a `Job`, verified when it is built
+pub struct JobBuilder {
+	config: Config,
+	targets: Option<targets::Targets>,
+}
+
+impl JobBuilder {
+	/// Language of the mnemonic word list. Detected from the known words of the stencil by default
+	pub fn language(mut self, language: bip39::Language) -> JobBuilder {
+		self.config.language = Some(language);
+		self
+	}
+
+	/// Solve for candidates in the range [start, end). Defaults to the entire problem space of the stencil, 2048 ^ unknown words
+	pub fn range(mut self, start: u64, end: u64) -> JobBuilder {
+		self.config.range = (start, end);
+		self
+	}
+
+	/// BIP39 passphrase (the "25th word") protecting the seed. Defaults to an empty passphrase
+	pub fn passphrase<S: Into<String>>(mut self, passphrase: S) -> JobBuilder {
+		self.config.passphrase = Some(passphrase.into());
+		self
+	}
+
+	/// Passphrase recovery: file of candidate passphrases, one per line. Requires a complete mnemonic as the stencil
+	pub fn passphrases<S: Into<String>>(mut self, path: S) -> JobBuilder {
+		self.config.passphrases = Some(path.into());
+		self
+	}
+
+	/// Passphrase recovery: hashcat-style mask of candidate passphrases, eg: `?u?l?l?l?d?d`. Requires a complete mnemonic as the stencil
+	pub fn mask<S: Into<String>>(mut self, mask: S) -> JobBuilder {
+		self.config.mask = Some(mask.into());
+		self
+	}
+
+	/// Derivation path template checked for every candidate, eg: `m/84'/0'/0'/{0,1}/0..20`, or one of the presets `evm` and `evm-ledger-live`.
+	/// Defaults to the first receiving address of the standard wallet of each address kind among the targets
+	pub fn path<S: Into<String>>(mut self, template: S) -> JobBuilder {
+		self.config.path.push(template.into());
+		self
+	}
+
+	/// Addresses searched for, the job runs on the network of the targets
+	pub fn targets(mut self, targets: targets::Targets) -> JobBuilder {
+		self.targets = Some(targets);
+		self
+	}
+
+	/// How many workgroups of 256 threads are dispatched at once in the derivation stage. Default is 64
+	pub fn dispatch(mut self, dispatch: u32) -> JobBuilder {
+		self.config.dispatch = Some(dispatch);
+		self
+	}
+
+	/// How many steps are in flight on the device at once. The filter pass of the next step runs while the last is derived and read back,
+	/// each step holds its own buffers of master extended keys, about 350 MiB. Default is 2
+	pub fn buffers(mut self, buffers: usize) -> JobBuilder {
+		self.config.buffers = Some(buffers);
+		self
+	}
+
+	pub fn build(self) -> Result<Job, Error> {
+		let JobBuilder { mut config, targets } = self;
+		let Some(targets) = targets else {
+			return Err(Error::config("Config", "A job requires targets"));
+		};
+
+		config.network = Some(targets.network());
+		utils::verify_config(&mut config)?;
+
+		let paths = match config.path.is_empty() {
+			true => paths::Paths::standard(targets.kinds(), targets.network()),
+			false => paths::Paths::new(&config.path).map_err(|err| Error::config("Path", err))?,
+		};
+
+		Ok(Job {
+			config,
+			targets: Arc::new(targets),
+			paths,
+		})
+	}
+}
@@ -0,0 +1,95 @@
+//! Generates the remaining words in a BIP39 seed phrase, or its passphrase, by brute-force on a WebGPU device or every CPU core.
+//!
+//! A [`Job`] describes the candidates to check and the [`targets::Targets`] they are checked against, a [`Solver`] runs it:
+//!
+//! ```no_run
+//! use webgpu_bip39_validator::{bitcoin, targets::Targets, Backend, Event, Job, Solver};
+//!
+//! let targets = Targets::read("addresses.txt", bitcoin::Network::Bitcoin).unwrap();
+//! let job = Job::builder("throw _ bulk opinion _ _ party exact guide _ change thought".split(' ')).targets(targets).build().unwrap();
+//!
+//! let solver = pollster::block_on(Solver::new(Backend::Gpu)).unwrap();
+//! solver.run(&job, |event| match event {
+//!     Event::Match(found) => println!("{}", found.mnemonic),
+//!     Event::Progress(progress) => println!("{}/{}", progress.completed, progress.steps),
+//! })
+//! .unwrap();
+//! ```
+
+pub mod checkpoint;
+pub(crate) mod device;
+pub mod distributed;
+pub(crate) mod error;
+pub mod index;
+pub(crate) mod job;
+pub mod metrics;
+pub mod output;
+pub mod paths;
+pub(crate) mod scan;
+pub(crate) mod solver;
+pub mod targets;
+pub(crate) mod utils;
+
+#[cfg(test)]
+pub(crate) mod tests;
+
+pub use bip39;
+pub use bitcoin;
+pub use device::{AdapterSelection, REQUIRED_FEATURES};
+pub use error::Error;
+pub use job::{Job, JobBuilder};
+pub use scan::{Backend, Event, Interrupt, Match, Progress, Solver, DEFAULT_QUEUE_DEPTH};
+pub use wgpu;
+
+/// Parameters of a scan, built and verified by a `JobBuilder`
+#[derive(Clone, Default)]
+pub(crate) struct Config {
+	/// known and unknown words of the mnemonic sentence
+	pub(crate) stencil: Vec<String>,
+	/// language of the mnemonic word list, detected from the known words of the stencil if unset
+	pub(crate) language: Option<bip39::Language>,
+	/// candidates in the range [start, end) are solved for
+	pub(crate) range: (u64, u64),
+	pub(crate) passphrase: Option<String>,
+	/// passphrase recovery: file of candidate passphrases
+	pub(crate) passphrases: Option<String>,
+	/// passphrase recovery: hashcat-style mask of candidate passphrases
+	pub(crate) mask: Option<String>,
+	pub(crate) network: Option<bitcoin::Network>,
+	/// derivation path templates, standard paths of the targets' address kinds are used if empty
+	pub(crate) path: Vec<String>,
+	/// how many workgroups of 256 threads are dispatched at once in the derivation stage
+	pub(crate) dispatch: Option<u32>,
+	/// how many steps are in flight on the device at once, each with its own set of buffers
+	pub(crate) buffers: Option<usize>,
+}
+
+impl Config {
+	pub(crate) fn network(&self) -> bitcoin::Network {
+		self.network.unwrap_or(bitcoin::Network::Bitcoin)
+	}
+}
+
+/// Parses a bitcoin network: bitcoin, mainnet, testnet, testnet4, signet or regtest
+pub fn parse_network(name: &str) -> Result<bitcoin::Network, String> {
+	match name.to_lowercase().as_str() {
+		"mainnet" | "main" => Ok(bitcoin::Network::Bitcoin),
+		name => name.parse().map_err(|_| format!("Unsupported network: {}", name)),
+	}
+}
+
+/// Parses the name of a BIP39 word list, ignoring case and separators, eg: `chinese-simplified` or `Japanese`
+pub fn parse_language(name: &str) -> Result<bip39::Language, String> {
+	let name = name.to_lowercase().replace(['-', '_', ' '], "");
+	let name = match name.as_str() {
+		"chinesesimplified" => "simplifiedchinese",
+		"chinesetraditional" => "traditionalchinese",
+		other => other,
+	};
+
+	bip39::Language::ALL
+		.iter()
+		.find(|l| l.to_string().to_lowercase() == name)
+		.copied()
+		.ok_or_else(|| format!("Unsupported language: {}", name))
+}
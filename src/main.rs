@@ -1,71 +1,194 @@
 use std::{
 	fs,
-	io::{BufRead, Write},
+	io::Write,
+	sync::{Arc, Mutex},
+};
+use webgpu_bip39_validator::{
+	bip39, bitcoin, checkpoint, distributed, metrics, output, targets, wgpu, AdapterSelection, Backend, Error, Event, Interrupt, Job, Match, Progress, Solver, DEFAULT_QUEUE_DEPTH, REQUIRED_FEATURES,
 };
 
-pub(crate) mod device;
-pub(crate) mod solver;
-pub mod utils;
-
-#[cfg(test)]
-pub(crate) mod tests;
-
-#[derive(argh::FromArgs, Clone, Default)]
+#[derive(argh::FromArgs, Clone)]
 /// Generates the remaining words in a BTC seed phrase by brute-force. Uses the WebGPU API
-pub(crate) struct Config {
-	/// string describing known and unknown words in the mnemonic sentence. Must be 12 words long
+struct Cli {
+	/// string describing known and unknown words in the mnemonic sentence. Must be 12, 15, 18, 21 or 24 words long.
+	/// unknown words are marked with `_`, partially known words with a pattern such as `ca*` or `?oo?`, or a set such as `{cat,car,cable}`
 	#[argh(positional)]
 	stencil: Vec<String>,
-	/// solve for addresses in the range [start, end]. Maximum problem space is [0, 17592186044416] (2^44)
-	#[argh(option, short = 'p', default = "(0,17592186044416)", from_str_fn(parse_partition))]
+	/// language of the mnemonic word list: english, simplified-chinese, traditional-chinese, czech, french, italian, japanese, korean, portuguese or spanish. Detected from the known words of the stencil by default
+	#[argh(option, short = 'l', from_str_fn(parse_language))]
+	language: Option<bip39::Language>,
+	/// solve for candidates in the range [start, end). Defaults to the entire problem space of the stencil, 2048 ^ unknown words
+	#[argh(option, short = 'p', default = "(0,u64::MAX)", from_str_fn(parse_partition))]
 	range: (u64, u64),
-	/// file containing list of known addresses to verify against
+	/// BIP39 passphrase (the "25th word") protecting the seed. Defaults to an empty passphrase
+	#[argh(option)]
+	passphrase: Option<String>,
+	/// passphrase recovery: file of candidate passphrases, one per line. Requires a complete mnemonic as the stencil
+	#[argh(option)]
+	passphrases: Option<String>,
+	/// passphrase recovery: hashcat-style mask of candidate passphrases, eg: `?u?l?l?l?d?d`. Requires a complete mnemonic as the stencil
+	#[argh(option)]
+	mask: Option<String>,
+	/// bitcoin network of the searched addresses: bitcoin, testnet, testnet4, signet or regtest. Defaults to bitcoin
+	#[argh(option, short = 'n', from_str_fn(parse_network))]
+	network: Option<bitcoin::Network>,
+	/// derivation path template checked for every candidate, repeatable. Levels may be ranges or sets, eg: `m/84'/0'/0'/{0,1}/0..20`,
+	/// or one of the presets `evm` and `evm-ledger-live`.
+	/// Defaults to the first receiving address of the BIP44, BIP49, BIP84 or BIP86 wallet of each address type searched for, with the network's coin type
+	#[argh(option)]
+	path: Vec<String>,
+	/// file containing list of known addresses to verify against, each optionally followed by a comma and a label. Or an index built by the `index` subcommand
 	#[argh(option, short = 'a')]
 	addresses: Option<String>,
 	/// file to which found addresses will be output
 	#[argh(option, short = 'f')]
 	found: Option<String>,
+	/// format of the matches written to the found file: text, jsonl or csv. Defaults to text
+	#[argh(option, from_str_fn(parse_output_format))]
+	output_format: Option<output::Format>,
+	/// file to which progress is saved after every step. Defaults to `checkpoint.txt`
+	#[argh(option)]
+	checkpoint: Option<String>,
+	/// continue from the checkpoint of an interrupted scan with the same stencil, range, passphrases, paths and addresses
+	#[argh(switch)]
+	resume: bool,
+	/// where the filter and derivation stages run: gpu or cpu. Defaults to gpu
+	#[argh(option, from_str_fn(parse_backend))]
+	backend: Option<Backend>,
+	/// GPU to run on by its index in `--list-adapters`, or a part of its name, repeatable. Steps are shared between the GPUs as they finish them.
+	/// Defaults to every usable GPU, each may only be selected once
+	#[argh(option)]
+	adapter: Vec<AdapterSelection>,
+	/// graphics backends adapters are enumerated on: vulkan, metal, dx12 or gl, separated by commas. Only the backends built into the binary are accepted. Defaults to `WGPU_BACKEND`, or every backend
+	#[argh(option, from_str_fn(parse_backends))]
+	backends: Option<wgpu::Backends>,
+	/// print every adapter of the enabled backends with its limits and supported features, then exit
+	#[argh(switch)]
+	list_adapters: bool,
 	/// how many threads to process per iteration in the derivation stage.
 	/// multiplied by 256 to arrive at final value. Default is 64
 	#[argh(option, short = 'd')]
 	dispatch: Option<u32>,
+	/// how many steps are in flight on the GPU at once, the filter stage of the next step runs while the last is derived and read back.
+	/// each step needs about 350 MiB of device memory. Default is 2
+	#[argh(option)]
+	buffers: Option<usize>,
+	/// how many steps of results may wait to be confirmed on the CPU before the solver stops submitting work. Default is 4
+	#[argh(option)]
+	queue_depth: Option<usize>,
+	/// address on which throughput, totals and the ETA are served to Prometheus at `/metrics`, eg: `127.0.0.1:9184`. Not served by default
+	#[argh(option)]
+	metrics: Option<String>,
+	/// scans the stencil when omitted. A stencil starting with the word `index` can write it as `{index}`
+	#[argh(subcommand)]
+	command: Option<Command>,
 }
 
-pub(crate) fn read_addresses_file(path: &str) -> gxhash::HashSet<solver::types::PublicKeyHash> {
-	let Ok(file) = fs::File::open(path) else {
-		log::error!("Create an `{}`, containing P2PKH addresses to test against", path);
-		std::process::exit(1);
-	};
+/// Subcommands other than scanning
+#[derive(argh::FromArgs, Clone, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+	Index(Index),
+	Coordinator(Coordinator),
+	Worker(Worker),
+}
 
-	let reader = std::io::BufReader::new(file);
-	reader.lines().map(Result::unwrap).map(|l| parse_address(&l).unwrap()).collect()
+#[derive(argh::FromArgs, Clone, PartialEq, Debug)]
+/// builds an index of targets from a list of addresses, such as a dump of the UTXO set. Pass the index as `--addresses` to scan against it
+#[argh(subcommand, name = "index")]
+struct Index {
+	/// file of addresses, one per line or in any column of a CSV file. Files ending in `.gz` are decompressed
+	#[argh(positional)]
+	input: String,
+	/// file to which the index is written
+	#[argh(option, short = 'o')]
+	output: String,
+	/// bitcoin network of the addresses: bitcoin, testnet, testnet4, signet or regtest. Defaults to bitcoin
+	#[argh(option, short = 'n', from_str_fn(parse_network))]
+	network: Option<bitcoin::Network>,
 }
 
-pub(crate) fn parse_address(address: &str) -> Result<solver::types::PublicKeyHash, String> {
-	use base58::FromBase58;
+#[derive(argh::FromArgs, Clone, PartialEq, Debug)]
+/// hands out leases of the stencil's steps to workers over TCP, writing their matches to the found file and their coverage to the checkpoint
+#[argh(subcommand, name = "coordinator")]
+struct Coordinator {
+	/// address on which workers connect. Defaults to 127.0.0.1:7878, other hosts can only reach the coordinator if it listens on their network
+	#[argh(option, default = "String::from(\"127.0.0.1:7878\")")]
+	listen: String,
+	/// secret shared with the workers, requests without it are refused. Defaults to the BIP39_TOKEN environment variable, which keeps it out of the process list
+	#[argh(option)]
+	token: Option<String>,
+	/// steps handed out per lease. Default is 16
+	#[argh(option)]
+	lease_steps: Option<u64>,
+	/// seconds a worker may be silent before its lease is handed to another worker. Default is 120
+	#[argh(option)]
+	lease_timeout: Option<u64>,
+}
 
-	let bytes = address.from_base58().unwrap();
+#[derive(argh::FromArgs, Clone, PartialEq, Debug)]
+/// checks leases of a coordinator's job. The stencil, range, passphrases and paths come from the coordinator, `--addresses` must hold the same targets
+#[argh(subcommand, name = "worker")]
+struct Worker {
+	/// address of the coordinator, eg: `192.168.1.2:7878`
+	#[argh(positional)]
+	coordinator: String,
+	/// name of the worker in the coordinator's progress. Defaults to the host name and process id
+	#[argh(option)]
+	name: Option<String>,
+	/// secret shared with the coordinator. Defaults to the BIP39_TOKEN environment variable, which keeps it out of the process list
+	#[argh(option)]
+	token: Option<String>,
+}
 
-	// P2PKH should be exactly 25 bytes
-	if bytes.len() != 25 {
-		return Err(format!("Invalid length: expected 25, got {}", bytes.len()));
+fn parse_backend(name: &str) -> Result<Backend, String> {
+	match name.to_lowercase().as_str() {
+		"gpu" => Ok(Backend::Gpu),
+		"cpu" => Ok(Backend::Cpu),
+		_ => Err(format!("Unsupported backend: {}, expected gpu or cpu", name)),
 	}
+}
+
+/// Backends must be compiled in, through the wgpu feature of the same name
+fn parse_backends(names: &str) -> Result<wgpu::Backends, String> {
+	names.split(',').try_fold(wgpu::Backends::empty(), |backends, name| {
+		let (backend, feature) = match name.trim().to_lowercase().as_str() {
+			"vulkan" | "vk" => (wgpu::Backends::VULKAN, "vulkan"),
+			"metal" | "mtl" => (wgpu::Backends::METAL, "metal"),
+			"dx12" | "d3d12" => (wgpu::Backends::DX12, "dx12"),
+			"gl" | "gles" | "opengl" => (wgpu::Backends::GL, "gles"),
+			_ => return Err(format!("Unsupported graphics backend: {}, expected vulkan, metal, dx12 or gl", name)),
+		};
+
+		match wgpu::Instance::enabled_backend_features().contains(backend) {
+			true => Ok(backends | backend),
+			false => Err(format!("Graphics backend {} isn't available, this binary was built without wgpu's `{}` feature", name.trim(), feature)),
+		}
+	})
+}
 
-	// Verify version byte (0x00 for mainnet P2PKH)
-	if bytes[0] != 0x00 {
-		return Err("Not a P2PKH address".to_string());
+fn parse_output_format(name: &str) -> Result<output::Format, String> {
+	match name.to_lowercase().as_str() {
+		"text" => Ok(output::Format::Text),
+		"jsonl" | "json" => Ok(output::Format::Jsonl),
+		"csv" => Ok(output::Format::Csv),
+		_ => Err(format!("Unsupported output format: {}, expected text, jsonl or csv", name)),
 	}
+}
+
+fn parse_network(name: &str) -> Result<bitcoin::Network, String> {
+	webgpu_bip39_validator::parse_network(name)
+}
 
-	// Extract the 20-byte hash160
-	let mut buf = [0u8; 20];
-	buf.copy_from_slice(&bytes[1..21]);
+fn parse_partition(path: &str) -> Result<(u64, u64), String> {
+	let invalid = || format!("Invalid range {}, expected start/end", path);
+	let (start, end) = path.split_once('/').ok_or_else(invalid)?;
 
-	Ok(buf)
+	Ok((start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?))
 }
 
-pub(crate) fn parse_partition(path: &str) -> Result<(u64, u64), String> {
-	let mut parts = path.split('/').take(2).map(|s| s.parse().unwrap());
-	Ok((parts.next().unwrap(), parts.next().unwrap()))
+fn parse_language(name: &str) -> Result<bip39::Language, String> {
+	webgpu_bip39_validator::parse_language(name)
 }
 
 #[pollster::main]
@@ -79,132 +202,427 @@ async fn main() {
 		.init()
 		.unwrap();
 
-	// acquire and verify config
-	let config: Config = argh::from_env();
-	utils::verify_config(&config);
+	// acquire config
+	let cli: Cli = argh::from_env();
 
-	// initialize device and device
-	let (device, queue) = device::init().await;
+	let result = match cli.command.as_ref() {
+		_ if cli.list_adapters => {
+			list_adapters(cli.backends.unwrap_or_else(|| wgpu::Backends::all().with_env())).await;
+			Ok(())
+		}
+		Some(Command::Index(index)) => webgpu_bip39_validator::index::build(&index.input, &index.output, index.network.unwrap_or(bitcoin::Network::Bitcoin)).map(drop),
+		Some(Command::Coordinator(coordinator)) => coordinate(&cli, coordinator),
+		Some(Command::Worker(worker)) => work(&cli, worker).await,
+		None => scan(&cli).await,
+	};
 
-	// start monitoring thread
-	let config_ = config.clone();
-	let mut then = std::time::Instant::now();
-	let (sender, receiver) = flume::unbounded::<solver::StageComputation>();
+	if let Err(err) = result {
+		log::error!("{}", err);
+		std::process::exit(err.exit_code());
+	}
+}
 
-	let handle = std::thread::spawn(move || {
-		log::debug!("Result collection thread has started");
+/// Prints every adapter of the backends, as indexed by `--adapter`
+async fn list_adapters(backends: wgpu::Backends) {
+	let adapters = Solver::adapters(backends).await;
+	if adapters.is_empty() {
+		println!("No adapters were found on the backends {:?}", backends);
+	}
 
-		// track progress
-		let steps = 1 + (config.range.1 - config.range.0) / solver::STEP as u64;
+	let supported = |supported: bool| if supported { "supported" } else { "missing" };
+	for (index, adapter) in adapters.iter().enumerate() {
+		let (info, features, limits) = (adapter.get_info(), adapter.features(), adapter.limits());
+
+		println!("{}: {} ({:?}, {:?})", index, info.name, info.backend, info.device_type);
+		println!("\tSHADER_INT64: {}", supported(features.contains(wgpu::Features::SHADER_INT64)));
+		println!("\tIMMEDIATES: {}", supported(features.contains(wgpu::Features::IMMEDIATES)));
+		println!("\tmax_buffer_size: {}", limits.max_buffer_size);
+		println!("\tmax_storage_buffer_binding_size: {}", limits.max_storage_buffer_binding_size);
+		println!("\tmax_immediate_size: {}", limits.max_immediate_size);
+		println!("\tmax_compute_workgroups_per_dimension: {}", limits.max_compute_workgroups_per_dimension);
+		println!("\tmax_compute_invocations_per_workgroup: {}", limits.max_compute_invocations_per_workgroup);
+
+		if !features.contains(REQUIRED_FEATURES) {
+			println!("\tunusable, the solver requires SHADER_INT64 and IMMEDIATES");
+		}
+	}
+}
 
-		// input and output files
-		let output_path = config.found.as_deref().unwrap_or("found.txt");
-		let Ok(mut output_file) = fs::OpenOptions::new().write(true).create(true).truncate(true).open(output_path) else {
-			log::error!("Create a `{}` file to output found addresses to", output_path);
-			std::process::exit(1);
-		};
+/// Scans the stencil against the addresses, writing matches to the found file
+async fn scan(cli: &Cli) -> Result<(), Error> {
+	let Some((job, fingerprint)) = job(cli)? else {
+		return Ok(());
+	};
 
-		let addresses_path = config.addresses.as_deref().unwrap_or("addresses.txt");
-		let addresses = read_addresses_file(addresses_path);
+	let solver = solver(cli).await?;
+	let mut found = Found::open(cli)?;
+	let mut tracker = Tracker::new(cli, &job, Some(fingerprint), "Device")?;
+	on_interrupt(solver.interrupt());
 
-		log::info!("Output Addresses = \"{}\", Input Addresses = \"{}\"", output_path, addresses_path);
-		log::debug!("Parsed Addresses Set: Len = {}", addresses.len());
+	let result = solver.run(&job, |event| match event {
+		Event::Match(found_match) => {
+			tracker.matched();
+			found.write(&found_match)
+		}
+		Event::Progress(progress) => tracker.report(&progress),
+	});
 
-		// bitcoin state
-		let secp256k1 = bitcoin::key::Secp256k1::new();
-		let derivation_path: bitcoin::bip32::DerivationPath = std::str::FromStr::from_str("m/44'/0'/0'/0/0").unwrap();
-		let _null_hash: [u32; 64] = bytemuck::Zeroable::zeroed();
+	if let Err(Error::Interrupted { covered }) = &result {
+		tracker.interrupted(covered);
+	}
 
-		// performance tracking
-		let mut found = 0u32;
+	found.finish(result)
+}
 
-		// consume messages
-		loop {
-			let count = receiver.len();
-			if count >= 64 {
-				log::error!(target: "main::monitoring_thread", "Severe Bottleneck from monitoring thread: Queue length = {}", receiver.len())
-			}
+/// Holds the job for workers, writing their matches to the found file and their coverage to the checkpoint
+fn coordinate(cli: &Cli, coordinator: &Coordinator) -> Result<(), Error> {
+	let Some((job, fingerprint)) = job(cli)? else {
+		return Ok(());
+	};
 
-			if count == 0 {
-				continue;
-			} else {
-				log::info!(target: "main::monitoring_thread", "Processing {} master extended keys", count);
-			}
+	let listener = std::net::TcpListener::bind(&coordinator.listen).map_err(|err| Error::Network(format!("Unable to listen on `{}`: {}", coordinator.listen, err)))?;
+	let mut found = Found::open(cli)?;
+	let mut tracker = Tracker::new(cli, &job, Some(fingerprint), "Worker")?;
+
+	let mut server = distributed::Coordinator::new(job).token(token(&coordinator.token)?);
+	if let Some(steps) = coordinator.lease_steps {
+		server = server.lease_steps(steps);
+	}
+	if let Some(seconds) = coordinator.lease_timeout {
+		server = server.lease_timeout(std::time::Duration::from_secs(seconds));
+	}
+
+	on_interrupt(server.interrupt());
+
+	let result = server.serve(listener, |event| match event {
+		Event::Match(found_match) => {
+			tracker.matched();
+			found.write(&found_match)
+		}
+		Event::Progress(progress) => tracker.report(&progress),
+	});
+
+	if let Err(Error::Interrupted { covered }) = &result {
+		tracker.interrupted(covered);
+	}
+
+	found.finish(result)
+}
+
+/// Secret shared by the coordinator and its workers, from `--token` or the environment
+fn token(option: &Option<String>) -> Result<String, Error> {
+	let token = option.clone().or_else(|| std::env::var("BIP39_TOKEN").ok()).unwrap_or_default();
+
+	match token.is_empty() {
+		true => Err(Error::Config {
+			field: "Token",
+			reason: "The coordinator and its workers share a secret, pass --token or set BIP39_TOKEN".to_string(),
+		}),
+		false => Ok(token),
+	}
+}
+
+/// Checks leases of a coordinator's job, matches are also written to the local found file
+async fn work(cli: &Cli, worker: &Worker) -> Result<(), Error> {
+	let name = worker
+		.name
+		.clone()
+		.unwrap_or_else(|| format!("{}-{}", std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".to_string()), std::process::id()));
+	let mut client = distributed::Worker::new(&worker.coordinator, name).token(token(&worker.token)?);
+
+	// the job's network is only known once the coordinator has described it
+	let addresses_path = cli.addresses.clone().unwrap_or_else(|| "addresses.txt".to_string());
+	let mut builder = client.job(|network| targets::Targets::read(&addresses_path, network))?;
+	if let Some(dispatch) = cli.dispatch {
+		builder = builder.dispatch(dispatch);
+	}
+	if let Some(buffers) = cli.buffers {
+		builder = builder.buffers(buffers);
+	}
+
+	let job = builder.build()?;
+	let solver = solver(cli).await?;
+	let mut found = Found::open(cli)?;
+	let mut tracker = Tracker::new(cli, &job, None, "Device")?;
+	on_interrupt(solver.interrupt());
+
+	let result = client.run(&solver, &job, |event| match event {
+		Event::Match(found_match) => {
+			tracker.matched();
+			found.write(&found_match)
+		}
+		Event::Progress(progress) => tracker.report(&progress),
+	});
+
+	if let Err(Error::Interrupted { covered }) = &result {
+		tracker.interrupted(covered);
+	}
+
+	found.finish(result)
+}
+
+/// Interrupts the job on the first Ctrl-C, the steps in flight are still checked and recorded. A second Ctrl-C exits immediately
+fn on_interrupt(interrupt: Interrupt) {
+	let result = ctrlc::set_handler(move || {
+		if interrupt.is_interrupted() {
+			log::error!("Interrupted again, exiting without checking the steps in flight");
+			std::process::exit(130);
+		}
+
+		log::warn!("Interrupted, checking the steps in flight. Press Ctrl-C again to exit immediately");
+		interrupt.interrupt();
+	});
+
+	if let Err(err) = result {
+		log::warn!("Unable to handle Ctrl-C: {}", err);
+	}
+}
+
+/// The job described by the command line and its fingerprint, continued from its checkpoint when resuming. `None` if the checkpoint has completed it
+fn job(cli: &Cli) -> Result<Option<(Job, String)>, Error> {
+	// targets, read from an address file or index
+	let addresses_path = cli.addresses.clone().unwrap_or_else(|| "addresses.txt".to_string());
+	let network = cli.network.unwrap_or(bitcoin::Network::Bitcoin);
+	let addresses = targets::Targets::read(&addresses_path, network)?;
+
+	// verify config
+	let mut builder = Job::builder(&cli.stencil).range(cli.range.0, cli.range.1).targets(addresses);
+	if let Some(language) = cli.language {
+		builder = builder.language(language);
+	}
+	if let Some(passphrase) = cli.passphrase.as_deref() {
+		builder = builder.passphrase(passphrase);
+	}
+	if let Some(passphrases) = cli.passphrases.as_deref() {
+		builder = builder.passphrases(passphrases);
+	}
+	if let Some(mask) = cli.mask.as_deref() {
+		builder = builder.mask(mask);
+	}
+	if let Some(dispatch) = cli.dispatch {
+		builder = builder.dispatch(dispatch);
+	}
+	if let Some(buffers) = cli.buffers {
+		builder = builder.buffers(buffers);
+	}
+	for template in cli.path.iter() {
+		builder = builder.path(template);
+	}
 
-			let mut max_step = 0;
-			let mut total = 0;
-
-			for solver::StageComputation { step, constants, outputs } in receiver.drain() {
-				max_step = max_step.max(step);
-				total += outputs.len();
-
-				// process master extended keys
-				for (_idx, output) in IntoIterator::into_iter(outputs).enumerate() {
-					#[cfg(debug_assertions)]
-					if output.hash == _null_hash {
-						log::error!("Step = {}, Thread = {}, Word2 = {}, returned a null result", step, _idx, output.word2);
-						continue;
-					}
-
-					// TODO: Partially move derivations to GPU
-					let combined = output.hash.map(|s| s as u8);
-
-					let mut chain_code_bytes = [0; 32];
-					chain_code_bytes.copy_from_slice(&combined[32..]);
-
-					// TODO: use custom code for key derivation
-					let master_extended_private_key = bitcoin::bip32::Xpriv {
-						network: bitcoin::NetworkKind::Main,
-						depth: 0,
-						parent_fingerprint: bitcoin::bip32::Fingerprint::from([0; 4]),
-						child_number: bitcoin::bip32::ChildNumber::Hardened { index: 0 },
-						private_key: bitcoin::secp256k1::SecretKey::from_slice(&combined[..32]).unwrap(),
-						chain_code: bitcoin::bip32::ChainCode::from(chain_code_bytes),
-					};
-
-					// derive child private key
-					let child_private_key = master_extended_private_key.derive_priv(&secp256k1, &derivation_path).unwrap();
-
-					// derive public key hash
-					let public_key = bitcoin::PublicKey::from_private_key(&secp256k1, &child_private_key.to_priv());
-					let public_key_hash = public_key.pubkey_hash();
-
-					let bytes: &[u8; 20] = public_key_hash.as_ref();
-					if addresses.contains(bytes) {
-						found += 1;
-
-						// assemble mnemonic sequence
-						let entropy = [constants.word0, constants.word1, output.word2, constants.word3];
-						let entropy_be = entropy.map(|e| e.to_be());
-						let mnemonic = bip39::Mnemonic::from_entropy(bytemuck::cast_slice(&entropy_be)).unwrap();
-
-						let first = mnemonic.words().next().unwrap();
-						let sequence = mnemonic.words().skip(1).fold(first.to_string(), |acc, nxt| acc + " " + nxt);
-
-						// write to output file
-						let p2pkh = bitcoin::Address::p2pkh(public_key, bitcoin::Network::Bitcoin);
-						let line = format!("Mnemonic = \"{}\", MasterExtendedKey = \"{}\",  P2PKH = \"{}\"\n", sequence, master_extended_private_key, p2pkh);
-
-						log::warn!("Found Match: {}", &line[..line.len() - 2]);
-						output_file.write_all(line.as_bytes()).unwrap();
-					}
-				}
+	let mut job = builder.build()?;
+
+	// fingerprint the job, and continue from its checkpoint when resuming
+	let checkpoint_path = cli.checkpoint.clone().unwrap_or_else(|| "checkpoint.txt".to_string());
+	let fingerprint = job.fingerprint()?;
+
+	if cli.resume {
+		let checkpoint = checkpoint::Checkpoint::load(&checkpoint_path)?;
+
+		job = match job.resume(&checkpoint)? {
+			Some(job) => job,
+			None => {
+				log::warn!("Checkpoint `{}` has already completed the scan", checkpoint_path);
+				return Ok(None);
 			}
+		};
+
+		log::info!("Resuming scan from {}, Range = {:?}", checkpoint.next, job.range());
+	}
+
+	log::info!("Input Addresses = \"{}\", Network = {}", addresses_path, network);
+	log::debug!("Parsed Addresses Set: Len = {}, Expanded Derivation Paths: Len = {}", job.targets().len(), job.paths().len());
+
+	Ok(Some((job, fingerprint)))
+}
 
-			// log performance
-			let progress = (max_step / solver::STEP as u64) + 1;
-			log::info!(target: "main::monitoring_thread", "[{:03}/{:03}]: {} Addresses processed in {:?}", progress, steps, total, then.elapsed());
-			then = std::time::Instant::now();
+/// Initializes devices and queues, unless solving on the CPU
+async fn solver(cli: &Cli) -> Result<Solver, Error> {
+	let solver = match cli.backend.unwrap_or(Backend::Gpu) {
+		Backend::Gpu if !cli.adapter.is_empty() || cli.backends.is_some() => Solver::with_adapters(cli.backends.unwrap_or_else(|| wgpu::Backends::all().with_env()), &cli.adapter).await?,
+		Backend::Cpu if !cli.adapter.is_empty() || cli.backends.is_some() => {
+			return Err(Error::Config {
+				field: "Adapter",
+				reason: "Adapters and graphics backends can only be selected for the gpu backend".to_string(),
+			})
+		}
+		backend => Solver::new(backend).await?,
+	};
+
+	Ok(solver.queue_depth(cli.queue_depth.unwrap_or(DEFAULT_QUEUE_DEPTH)))
+}
+
+/// Matches written to the found file
+struct Found {
+	file: fs::File,
+	path: String,
+	format: output::Format,
+	count: u32,
+	/// the scan runs to completion if the found file fails, matches are still logged
+	failed: Option<Error>,
+}
+
+impl Found {
+	/// Matches of the interrupted scan are kept when resuming
+	fn open(cli: &Cli) -> Result<Found, Error> {
+		let path = cli.found.clone().unwrap_or_else(|| "found.txt".to_string());
+		let output_error = |err: std::io::Error| Error::Output(format!("`{}`: {}", path, err));
+		let mut file = fs::OpenOptions::new().write(true).create(true).append(cli.resume).truncate(!cli.resume).open(&path).map_err(output_error)?;
+
+		// csv files start with a header, unless appending to an existing file
+		let format = cli.output_format.unwrap_or_default();
+		if let Some(header) = output::Record::header(format).filter(|_| file.metadata().map_or(true, |m| m.len() == 0)) {
+			file.write_all(header.as_bytes()).map_err(output_error)?;
+		}
 
-			// break if we are done
-			if progress == steps {
-				break found;
+		log::info!("Output Addresses = \"{}\"", path);
+
+		Ok(Found {
+			file,
+			path,
+			format,
+			count: 0,
+			failed: None,
+		})
+	}
+
+	fn write(&mut self, found_match: &Match) {
+		self.count += 1;
+		let record = found_match.record();
+
+		// write to output file
+		log::warn!("Found Match: {}", record.format(output::Format::Text).trim_end());
+		if let Err(err) = self.file.write_all(record.format(self.format).as_bytes()) {
+			self.failed.get_or_insert(Error::Output(format!("`{}`: {}", self.path, err)));
+		}
+	}
+
+	/// Flushes the found file to disk. Fails with the first error writing to it, or else with the job's
+	fn finish(mut self, result: Result<(), Error>) -> Result<(), Error> {
+		if let Err(err) = self.file.flush().and_then(|_| self.file.sync_all()) {
+			self.failed.get_or_insert(Error::Output(format!("`{}`: {}", self.path, err)));
+		}
+
+		match &result {
+			Ok(()) => log::warn!("Completed Scan, Found: {} Matches", self.count),
+			Err(Error::Interrupted { .. }) => log::warn!("Interrupted Scan, Found: {} Matches", self.count),
+			Err(_) => (),
+		}
+
+		match self.failed {
+			Some(err) => Err(err),
+			None => result,
+		}
+	}
+}
+
+/// Logs the progress of every device or worker with the job's throughput, saving it to the checkpoint of the job with `fingerprint`
+struct Tracker {
+	fingerprint: Option<String>,
+	/// start of the job's range, the checkpoint records the candidates checked from it
+	start: u64,
+	path: String,
+	/// what the progress is reported by
+	label: &'static str,
+	then: Vec<std::time::Instant>,
+	steps: Vec<u64>,
+	/// shared with the metrics endpoint, if served
+	metrics: Arc<Mutex<metrics::Metrics>>,
+}
+
+impl Tracker {
+	fn new(cli: &Cli, job: &Job, fingerprint: Option<String>, label: &'static str) -> Result<Tracker, Error> {
+		let metrics = Arc::new(Mutex::new(metrics::Metrics::new(job)));
+		if let Some(address) = cli.metrics.as_deref() {
+			let listener = std::net::TcpListener::bind(address).map_err(|err| Error::Network(format!("Unable to serve metrics on `{}`: {}", address, err)))?;
+			metrics::serve(listener, metrics.clone());
+		}
+
+		Ok(Tracker {
+			fingerprint,
+			start: job.range().0,
+			path: cli.checkpoint.clone().unwrap_or_else(|| "checkpoint.txt".to_string()),
+			label,
+			then: Vec::new(),
+			steps: Vec::new(),
+			metrics,
+		})
+	}
+
+	fn matched(&mut self) {
+		self.metrics.lock().unwrap().record_match();
+	}
+
+	fn report(&mut self, progress: &Progress) {
+		// devices are known up front, workers as they join
+		let device = progress.device;
+		if device >= self.then.len() {
+			self.then.resize(device + 1, std::time::Instant::now());
+			self.steps.resize(device + 1, 0);
+		}
+
+		let (filter_rate, derivation_rate, eta) = {
+			let mut metrics = self.metrics.lock().unwrap();
+			metrics.record(progress);
+
+			let (filter_rate, derivation_rate) = metrics.rates();
+			(filter_rate, derivation_rate, metrics.eta())
+		};
+
+		// log performance
+		log::info!(
+			target: "main::monitoring_thread",
+			"[{:03}/{:03}] {} {}: {} Addresses processed in {:?}. Filter: {:.0}/s, Derivation: {:.0}/s, ETA: {}",
+			progress.completed,
+			progress.steps,
+			self.label,
+			device,
+			progress.derived,
+			self.then[device].elapsed(),
+			filter_rate,
+			derivation_rate,
+			eta.map_or_else(|| "unknown".to_string(), format_duration)
+		);
+
+		self.then[device] = std::time::Instant::now();
+		self.steps[device] += 1;
+
+		// save progress
+		self.save(progress.next);
+	}
+
+	/// Records the part of the range covered from its start before an interrupt, the scan continues after it with `--resume`
+	fn interrupted(&self, covered: &[(u64, u64)]) {
+		let next = covered.iter().find(|range| range.0 == self.start).map_or(self.start, |range| range.1);
+
+		if self.save(next) {
+			log::warn!("Checkpoint `{}` records candidates in [{}, {}) as checked, continue the scan with --resume", self.path, self.start, next);
+		}
+	}
+
+	/// Saves every candidate before `next` as checked, returns whether the checkpoint was saved
+	fn save(&self, next: u64) -> bool {
+		let Some(fingerprint) = self.fingerprint.clone() else {
+			return false;
+		};
+
+		let checkpoint = checkpoint::Checkpoint {
+			fingerprint,
+			next,
+			devices: self.steps.clone(),
+		};
+
+		match checkpoint.save(&self.path) {
+			Ok(()) => true,
+			Err(err) => {
+				log::error!(target: "main::monitoring_thread", "{}", err);
+				false
 			}
 		}
-	});
+	}
+}
 
-	// solve
-	solver::solve(&config_, &device, &queue, sender);
-	let found = handle.join().expect("Monitoring thread experienced an error");
-	log::warn!("Completed Scan, Found: {} Matches", found);
+/// Formats a duration as hours, minutes and seconds
+fn format_duration(duration: std::time::Duration) -> String {
+	let seconds = duration.as_secs();
+	format!("{}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
 }
@@ -0,0 +1,194 @@
+use super::*;
+use std::{
+	collections::VecDeque,
+	fmt::Write as _,
+	io::{BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Rates are averaged over the steps checked this long ago or less
+pub const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a scrape may take to arrive, a stalled connection doesn't hold up the next scrape
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Throughput, totals and an estimate of the time remaining of a job, from the progress of its steps
+pub struct Metrics {
+	started: Instant,
+	/// candidates in the range of the job
+	candidates: u64,
+	/// steps checked within the rate window, as (time, filtered, derived)
+	window: VecDeque<(Instant, u64, u64)>,
+	filtered: u64,
+	derived: u64,
+	matches: u64,
+	/// steps checked by each device or worker
+	devices: Vec<u64>,
+	completed: u64,
+	steps: u64,
+}
+
+impl Metrics {
+	/// Metrics of a job, from when it starts
+	pub fn new(job: &Job) -> Metrics {
+		let (start, end) = job.range();
+
+		Metrics {
+			started: Instant::now(),
+			candidates: end - start,
+			window: VecDeque::new(),
+			filtered: 0,
+			derived: 0,
+			matches: 0,
+			devices: Vec::new(),
+			completed: 0,
+			steps: job.steps(),
+		}
+	}
+
+	/// Counts a step checked by a device
+	pub fn record(&mut self, progress: &Progress) {
+		let now = Instant::now();
+		self.window.push_back((now, progress.filtered, progress.derived as u64));
+		self.expire(now);
+
+		self.filtered += progress.filtered;
+		self.derived += progress.derived as u64;
+		self.completed = progress.completed;
+		self.steps = progress.steps;
+
+		if progress.device >= self.devices.len() {
+			self.devices.resize(progress.device + 1, 0);
+		}
+		self.devices[progress.device] += 1;
+	}
+
+	/// Counts a confirmed match
+	pub fn record_match(&mut self) {
+		self.matches += 1;
+	}
+
+	/// Candidates per second checked against the checksum, and derived, over the rate window
+	pub fn rates(&mut self) -> (f64, f64) {
+		let now = Instant::now();
+		self.expire(now);
+
+		// rates over the time the job has run, until it has run for a whole window
+		let elapsed = now.duration_since(self.started).min(RATE_WINDOW).as_secs_f64();
+		if elapsed == 0.0 {
+			return (0.0, 0.0);
+		}
+
+		let (filtered, derived) = self.window.iter().fold((0, 0), |(filtered, derived), sample| (filtered + sample.1, derived + sample.2));
+		(filtered as f64 / elapsed, derived as f64 / elapsed)
+	}
+
+	/// Time left to check the rest of the range at the current rate, `None` until a step is checked
+	pub fn eta(&mut self) -> Option<Duration> {
+		let (rate, _) = self.rates();
+		let remaining = self.candidates.saturating_sub(self.filtered);
+
+		(rate > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / rate))
+	}
+
+	/// Candidates checked against the checksum and derived by every device
+	pub fn totals(&self) -> (u64, u64) {
+		(self.filtered, self.derived)
+	}
+
+	/// Metrics in the Prometheus text exposition format
+	pub fn render(&mut self) -> String {
+		let (filter_rate, derivation_rate) = self.rates();
+		let eta = self.eta().map_or(f64::NAN, |eta| eta.as_secs_f64());
+
+		let mut text = String::new();
+		let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+			let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+			for (labels, value) in samples {
+				let _ = writeln!(text, "{}{} {}", name, labels, value);
+			}
+		};
+
+		let stages = |filter: String, derivation: String| vec![("{stage=\"filter\"}".to_string(), filter), ("{stage=\"derivation\"}".to_string(), derivation)];
+		metric(
+			"bip39_candidates_total",
+			"counter",
+			"Candidates checked against the checksum by the filter stage, and derived by the derivation stage",
+			&stages(self.filtered.to_string(), self.derived.to_string()),
+		);
+		metric(
+			"bip39_candidates_per_second",
+			"gauge",
+			"Rate of candidates through each stage over the last minute",
+			&stages(filter_rate.to_string(), derivation_rate.to_string()),
+		);
+		metric("bip39_range_candidates", "gauge", "Candidates in the range of the job", &[(String::new(), self.candidates.to_string())]);
+		metric("bip39_steps_completed", "gauge", "Steps of the job checked so far", &[(String::new(), self.completed.to_string())]);
+		metric("bip39_steps", "gauge", "Steps covering the range of the job", &[(String::new(), self.steps.to_string())]);
+		metric(
+			"bip39_eta_seconds",
+			"gauge",
+			"Time left to check the rest of the range at the current rate",
+			&[(String::new(), eta.to_string())],
+		);
+		metric("bip39_matches_total", "counter", "Matches confirmed against the targets", &[(String::new(), self.matches.to_string())]);
+
+		let devices = self
+			.devices
+			.iter()
+			.enumerate()
+			.map(|(device, steps)| (format!("{{device=\"{}\"}}", device), steps.to_string()))
+			.collect::<Vec<_>>();
+		metric("bip39_device_steps_total", "counter", "Steps checked by each device or worker", &devices);
+
+		text
+	}
+
+	/// Drops steps checked before the rate window
+	fn expire(&mut self, now: Instant) {
+		while self.window.front().is_some_and(|sample| now.duration_since(sample.0) > RATE_WINDOW) {
+			self.window.pop_front();
+		}
+	}
+}
+
+/// Serves the metrics over HTTP at `/metrics` on a background thread, for as long as the process runs
+pub fn serve(listener: TcpListener, metrics: Arc<Mutex<Metrics>>) {
+	if let Ok(address) = listener.local_addr() {
+		log::info!(target: "metrics", "Serving metrics on http://{}/metrics", address);
+	}
+
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			let result = stream.and_then(|stream| respond(stream, &metrics));
+			if let Err(err) = result {
+				log::debug!(target: "metrics", "Scrape failed: {}", err);
+			}
+		}
+	});
+}
+
+/// Answers a single request, with the metrics or `404 Not Found`
+fn respond(stream: TcpStream, metrics: &Mutex<Metrics>) -> std::io::Result<()> {
+	stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+	stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+	let mut request = String::new();
+	BufReader::new(&stream).read_line(&mut request)?;
+
+	let (status, body) = match request.split_whitespace().nth(1) {
+		Some("/metrics") => ("200 OK", metrics.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).render()),
+		_ => ("404 Not Found", "Metrics are served at /metrics\n".to_string()),
+	};
+
+	let response = format!(
+		"HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		status,
+		body.len(),
+		body
+	);
+
+	(&stream).write_all(response.as_bytes())
+}
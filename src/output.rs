@@ -0,0 +1,106 @@
+/// Format of the records written to the found file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Format {
+	/// `Name = "value"` pairs, one match per line
+	#[default]
+	Text,
+	/// a JSON object per line
+	Jsonl,
+	/// comma separated values, with a header row
+	Csv,
+}
+
+/// A match found during a scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+	pub mnemonic: String,
+	/// entropy of the mnemonic, as hex
+	pub entropy: String,
+	pub passphrase: String,
+	pub master_extended_key: String,
+	pub path: String,
+	pub address_type: String,
+	pub address: String,
+	/// label of the matched target in the address file, empty if it has none
+	pub label: String,
+	/// index of the candidate among the candidates of the stencil, or the passphrases
+	pub candidate: u64,
+	/// candidates of the step the match was found in, written as `start/end` like `--range`
+	pub range: (u64, u64),
+}
+
+impl Record {
+	/// Names of the fields, in the order of the CSV columns
+	pub const FIELDS: [&str; 10] = ["mnemonic", "entropy", "passphrase", "master_extended_key", "path", "address_type", "address", "label", "candidate", "range"];
+
+	fn values(&self) -> [String; 10] {
+		[
+			self.mnemonic.clone(),
+			self.entropy.clone(),
+			self.passphrase.clone(),
+			self.master_extended_key.clone(),
+			self.path.clone(),
+			self.address_type.clone(),
+			self.address.clone(),
+			self.label.clone(),
+			self.candidate.to_string(),
+			format!("{}/{}", self.range.0, self.range.1),
+		]
+	}
+
+	/// Line written before the first record of a file, if the format has one
+	pub fn header(format: Format) -> Option<String> {
+		match format {
+			Format::Csv => Some(format!("{}\n", Self::FIELDS.join(","))),
+			_ => None,
+		}
+	}
+
+	/// The record as a single line, including its line break
+	pub fn format(&self, format: Format) -> String {
+		match format {
+			Format::Text => format!(
+				"Mnemonic = \"{}\", Passphrase = \"{}\", MasterExtendedKey = \"{}\", Path = \"{}\", Address = \"{}\"\n",
+				self.mnemonic, self.passphrase, self.master_extended_key, self.path, self.address
+			),
+			Format::Jsonl => {
+				let fields = Self::FIELDS.iter().zip(self.values()).map(|(&name, value)| match name {
+					"candidate" => format!("\"{}\":{}", name, value),
+					_ => format!("\"{}\":{}", name, json_string(&value)),
+				});
+
+				format!("{{{}}}\n", fields.collect::<Vec<_>>().join(","))
+			}
+			Format::Csv => format!("{}\n", self.values().iter().map(|value| csv_field(value)).collect::<Vec<_>>().join(",")),
+		}
+	}
+}
+
+/// Quoted JSON string, escaping quotes, backslashes and control characters
+fn json_string(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len() + 2);
+	escaped.push('"');
+
+	for char in value.chars() {
+		match char {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			char if char.is_control() => escaped.push_str(&format!("\\u{:04x}", char as u32)),
+			char => escaped.push(char),
+		}
+	}
+
+	escaped.push('"');
+	escaped
+}
+
+/// CSV field, quoted if it contains a separator, quote or line break. Quotes are doubled
+fn csv_field(value: &str) -> String {
+	match value.contains([',', '"', '\n', '\r']) {
+		true => format!("\"{}\"", value.replace('"', "\"\"")),
+		false => value.to_string(),
+	}
+}
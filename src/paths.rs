@@ -0,0 +1,162 @@
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::secp256k1;
+
+/// Named templates of common wallets: the first 20 accounts of MetaMask style and Ledger Live style EVM wallets
+pub(crate) const PRESETS: [(&str, &str); 2] = [("evm", "m/44'/60'/0'/0/0..20"), ("evm-ledger-live", "m/44'/60'/0..20'/0/0")];
+
+/// Upper bound on the number of paths a set of templates expands into, each path is derived for every candidate
+pub(crate) const MAX_PATHS: usize = 65536;
+
+/// Derivation paths checked for every candidate, grouped by their parent so its key is derived once for all of its children
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+	groups: Vec<(DerivationPath, Vec<ChildNumber>)>,
+}
+
+impl Paths {
+	/// Expands derivation path templates such as `m/84'/0'/0'/{0,1}/0..20`.
+	/// Each level is an index, an exclusive range `a..b` or a set `{a,b,c..d}`, followed by `'` or `h` if hardened. Templates may also name one of `PRESETS`
+	pub fn new<S: AsRef<str>>(templates: &[S]) -> Result<Paths, String> {
+		let mut groups: Vec<(DerivationPath, Vec<ChildNumber>)> = Vec::new();
+		// position of each parent in groups, and the children already in each group
+		let mut parents = std::collections::HashMap::<DerivationPath, usize>::new();
+		let mut seen = std::collections::HashSet::<(usize, ChildNumber)>::new();
+
+		for template in templates {
+			let template = template.as_ref();
+			let template = PRESETS.iter().find(|(name, _)| *name == template).map_or(template, |(_, preset)| preset);
+
+			for path in expand(template)? {
+				if path.len() > super::solver::types::Path::MAX_DEPTH {
+					return Err(format!("Derivation path {} is deeper than {} levels", template, super::solver::types::Path::MAX_DEPTH));
+				}
+
+				let (parent, child) = match path.split_last() {
+					Some((child, parent)) => (DerivationPath::from(parent.to_vec()), *child),
+					None => return Err("Derivation path can't be the master key".to_string()),
+				};
+
+				let group = *parents.entry(parent.clone()).or_insert_with(|| {
+					groups.push((parent, Vec::new()));
+					groups.len() - 1
+				});
+
+				// duplicated paths are derived once
+				if !seen.insert((group, child)) {
+					continue;
+				}
+
+				if seen.len() > MAX_PATHS {
+					return Err(format!("Derivation path templates expand into more than {} paths", MAX_PATHS));
+				}
+
+				groups[group].1.push(child);
+			}
+		}
+
+		Ok(Paths { groups })
+	}
+
+	/// First receiving address of the standard wallet path of each address kind, eg: `m/84'/0'/0'/0/0` for P2WPKH on mainnet or `m/44'/60'/0'/0/0` for EVM
+	pub fn standard(kinds: &[super::targets::AddressKind], network: bitcoin::Network) -> Paths {
+		let templates = kinds.iter().map(|kind| format!("m/{}'/{}'/0'/0/0", kind.purpose(), kind.coin_type(network))).collect::<Vec<_>>();
+		Paths::new(&templates).expect("Standard derivation paths should be valid")
+	}
+
+	pub fn len(&self) -> usize {
+		self.groups.iter().map(|(_, children)| children.len()).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.groups.is_empty()
+	}
+
+	/// Parent of each group of paths with its children, paths are numbered in this order
+	pub(crate) fn groups(&self) -> impl Iterator<Item = (&DerivationPath, &[ChildNumber])> {
+		self.groups.iter().map(|(parent, children)| (parent, children.as_slice()))
+	}
+
+	/// Every path, in the order they are derived
+	pub fn iter(&self) -> impl Iterator<Item = DerivationPath> + '_ {
+		self.groups.iter().flat_map(|(parent, children)| children.iter().map(|&child| parent.child(child)))
+	}
+
+	/// Derives the private key at every path, stopping early once `found` returns true
+	pub(crate) fn derive<C: secp256k1::Signing, F: FnMut(DerivationPath, Xpriv) -> bool>(&self, secp256k1: &secp256k1::Secp256k1<C>, master: &Xpriv, mut found: F) {
+		for (parent, children) in self.groups.iter() {
+			let parent_key = master.derive_priv(secp256k1, parent).expect("Derivation should not fail for valid private keys");
+
+			for &child in children {
+				let child_key = parent_key.derive_priv(secp256k1, &[child]).expect("Derivation should not fail for valid private keys");
+				if found(parent.child(child), child_key) {
+					return;
+				}
+			}
+		}
+	}
+}
+
+/// Expands a single template into its derivation paths
+fn expand(template: &str) -> Result<Vec<Vec<ChildNumber>>, String> {
+	let mut levels = template.split('/');
+	if levels.next() != Some("m") {
+		return Err(format!("Derivation path {} should start with `m`", template));
+	}
+
+	let mut paths = vec![Vec::<ChildNumber>::new()];
+	for level in levels {
+		let children = children(level).map_err(|err| format!("Invalid level `{}` in derivation path {}: {}", level, template, err))?;
+
+		if paths.len() * children.len() > MAX_PATHS {
+			return Err(format!("Derivation path {} expands into more than {} paths", template, MAX_PATHS));
+		}
+
+		paths = paths.into_iter().flat_map(|path| children.iter().map(move |&child| [path.as_slice(), &[child]].concat())).collect();
+	}
+
+	Ok(paths)
+}
+
+/// Child numbers of a single level of a template
+fn children(level: &str) -> Result<Vec<ChildNumber>, String> {
+	let (level, hardened) = match level.strip_suffix(['\'', 'h']) {
+		Some(level) => (level, true),
+		None => (level, false),
+	};
+
+	let items = match level.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+		Some(inner) => inner.split(',').collect::<Vec<_>>(),
+		None => vec![level],
+	};
+
+	let mut children = Vec::new();
+	for item in items {
+		let (start, end) = match item.split_once("..") {
+			Some((start, end)) => (parse_index(start)?, parse_index(end)?),
+			None => parse_index(item).map(|index| (index, index.saturating_add(1)))?,
+		};
+
+		if start >= end {
+			return Err(format!("Range {} is empty", item));
+		}
+
+		if (end - start) as usize > MAX_PATHS {
+			return Err(format!("Range {} is larger than {}", item, MAX_PATHS));
+		}
+
+		for index in start..end {
+			let child = match hardened {
+				true => ChildNumber::from_hardened_idx(index),
+				false => ChildNumber::from_normal_idx(index),
+			};
+
+			children.push(child.map_err(|err| err.to_string())?);
+		}
+	}
+
+	Ok(children)
+}
+
+fn parse_index(index: &str) -> Result<u32, String> {
+	index.parse().map_err(|_| format!("`{}` is not a child index", index))
+}
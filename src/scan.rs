@@ -0,0 +1,344 @@
+use super::*;
+
+/// Compute backend of the solver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+	/// filter and derivation passes on a wgpu device
+	Gpu,
+	/// the same pipeline on every CPU core
+	Cpu,
+}
+
+/// Something that happened while running a job
+#[derive(Debug, Clone)]
+pub enum Event {
+	/// a candidate paying to one of the targets, confirmed on the CPU
+	Match(Box<Match>),
+	/// steps were checked against the targets
+	Progress(Progress),
+}
+
+/// A candidate paying to one of the targets
+#[derive(Debug, Clone)]
+pub struct Match {
+	pub mnemonic: bip39::Mnemonic,
+	pub passphrase: String,
+	pub master_extended_key: bitcoin::bip32::Xpriv,
+	pub path: bitcoin::bip32::DerivationPath,
+	pub target: targets::Target,
+	/// label of the target in the address file
+	pub label: Option<String>,
+	/// index of the candidate among the candidates of the stencil, or the passphrases
+	pub candidate: u64,
+	/// candidates of the step the match was found in
+	pub range: (u64, u64),
+}
+
+impl Match {
+	/// The match as a record of the found file
+	pub fn record(&self) -> output::Record {
+		output::Record {
+			mnemonic: self.mnemonic.words().collect::<Vec<_>>().join(solver::stencil::separator(self.mnemonic.language())),
+			entropy: hex::encode(self.mnemonic.to_entropy()),
+			passphrase: self.passphrase.clone(),
+			master_extended_key: self.master_extended_key.to_string(),
+			path: format!("m/{}", self.path),
+			address_type: targets::AddressKind::of(&self.target).map_or("", |kind| kind.name()).to_string(),
+			address: self.target.to_string(),
+			label: self.label.clone().unwrap_or_default(),
+			candidate: self.candidate,
+			range: self.range,
+		}
+	}
+}
+
+/// A step checked by one of the devices
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Progress {
+	/// index of the device that checked the step, always 0 on the CPU
+	pub device: usize,
+	/// every candidate of the job before this index has been checked against the targets.
+	/// devices finish steps out of order, steps checked past a step still in flight don't move it
+	pub next: u64,
+	/// steps of the job checked so far by every device, out of `steps`
+	pub completed: u64,
+	pub steps: u64,
+	/// candidates of the step, each checked against the checksum unless recovering a passphrase
+	pub filtered: u64,
+	/// number of master extended keys derived in the step
+	pub derived: usize,
+}
+
+/// Steps whose results may wait for the CPU before the solver stops submitting work, by default
+pub const DEFAULT_QUEUE_DEPTH: usize = 4;
+
+/// Stops a running solver, or coordinator, from handing out more steps. Steps already claimed are still checked and reported
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl Interrupt {
+	pub fn interrupt(&self) {
+		self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+	}
+
+	pub fn is_interrupted(&self) -> bool {
+		self.0.load(std::sync::atomic::Ordering::Relaxed)
+	}
+}
+
+/// Runs jobs on wgpu devices sharing the steps of a job, or on every CPU core
+#[derive(Clone)]
+pub struct Solver {
+	backend: Backend,
+	devices: Vec<(wgpu::Device, wgpu::Queue)>,
+	queue_depth: usize,
+	/// shared by clones of the solver
+	interrupt: Interrupt,
+}
+
+impl Solver {
+	/// Solver on the backend, opening every usable GPU
+	pub async fn new(backend: Backend) -> Result<Solver, Error> {
+		match backend {
+			Backend::Gpu => Ok(Solver::with_devices(device::init_all(wgpu::Backends::all().with_env(), &[]).await?)),
+			Backend::Cpu => Ok(Solver {
+				backend,
+				devices: Vec::new(),
+				queue_depth: DEFAULT_QUEUE_DEPTH,
+				interrupt: Interrupt::default(),
+			}),
+		}
+	}
+
+	/// Solver on the selected adapters of the backends, which must support `IMMEDIATES` and `SHADER_INT64`. Every usable GPU is opened if none are selected
+	pub async fn with_adapters(backends: wgpu::Backends, adapters: &[AdapterSelection]) -> Result<Solver, Error> {
+		Ok(Solver::with_devices(device::init_all(backends, adapters).await?))
+	}
+
+	/// Every adapter of the backends, in the order `AdapterSelection::Index` refers to them. Adapters lacking any of [`REQUIRED_FEATURES`] can't be selected
+	pub async fn adapters(backends: wgpu::Backends) -> Vec<wgpu::Adapter> {
+		device::adapters(backends).await
+	}
+
+	/// Solver on a device created by the caller, which must support `IMMEDIATES` and `SHADER_INT64`
+	pub fn with_device(device: wgpu::Device, queue: wgpu::Queue) -> Solver {
+		Solver::with_devices([(device, queue)])
+	}
+
+	/// Solver sharing the steps of a job between devices created by the caller
+	pub fn with_devices<I: IntoIterator<Item = (wgpu::Device, wgpu::Queue)>>(devices: I) -> Solver {
+		Solver {
+			backend: Backend::Gpu,
+			devices: devices.into_iter().collect(),
+			queue_depth: DEFAULT_QUEUE_DEPTH,
+			interrupt: Interrupt::default(),
+		}
+	}
+
+	/// How many steps of results may wait to be confirmed on the CPU. Once they do, the solver waits before submitting the next step
+	pub fn queue_depth(mut self, depth: usize) -> Solver {
+		self.queue_depth = depth.max(1);
+		self
+	}
+
+	/// Interrupts the jobs running on the solver, and any it runs afterwards
+	pub fn interrupt(&self) -> Interrupt {
+		self.interrupt.clone()
+	}
+
+	pub fn backend(&self) -> Backend {
+		self.backend
+	}
+
+	/// Number of devices sharing the steps, progress is reported by device index. The CPU counts as a single device
+	pub fn devices(&self) -> usize {
+		match self.backend {
+			Backend::Gpu => self.devices.len(),
+			Backend::Cpu => 1,
+		}
+	}
+
+	/// Scans every candidate of the job, calling `callback` with each confirmed match and after each checked step.
+	/// Returns once the job's range is covered, or with the error that stopped a device. A failed device stops the others from claiming more steps.
+	/// Once interrupted, the steps in flight are checked and reported before failing with the part of the range that was covered
+	pub fn run<F: FnMut(Event)>(&self, job: &Job, mut callback: F) -> Result<(), Error> {
+		if self.devices() == 0 {
+			return Err(Error::Device("No devices to run the job on".to_string()));
+		}
+
+		let (sender, receiver) = flume::bounded::<solver::StageComputation>(self.queue_depth);
+		let steps = solver::Steps::new(&job.config).interrupted_by(&self.interrupt);
+
+		std::thread::scope(|scope| {
+			let handles = match self.backend {
+				Backend::Gpu => self
+					.devices
+					.iter()
+					.enumerate()
+					.map(|(index, (device, queue))| {
+						let (sender, steps) = (sender.clone(), &steps);
+
+						scope.spawn(move || {
+							let result = solver::solve(&job.config, &job.targets, &job.paths, device, queue, steps, index, sender);
+							if result.is_err() {
+								steps.stop();
+							}
+
+							result
+						})
+					})
+					.collect::<Vec<_>>(),
+				Backend::Cpu => {
+					let (sender, steps) = (sender.clone(), &steps);
+					vec![scope.spawn(move || {
+						let result = solver::cpu::solve(&job.config, &job.targets, &job.paths, steps, sender);
+						if result.is_err() {
+							steps.stop();
+						}
+
+						result
+					})]
+				}
+			};
+
+			// the receiver disconnects once every solver thread has stopped. A failed confirmation stops the devices and is returned over their errors
+			drop(sender);
+			let confirmed = confirm(job, receiver, &mut callback);
+			if confirmed.is_err() {
+				steps.stop();
+			}
+
+			let results = handles
+				.into_iter()
+				.enumerate()
+				.map(|(device, handle)| handle.join().unwrap_or_else(|_| Err(Error::Device(format!("Solver thread of device {} panicked", device)))))
+				.collect::<Vec<_>>();
+
+			let next = confirmed?;
+			results.into_iter().collect::<Result<(), Error>>()?;
+
+			// every claimed step was checked, an interrupt after the last step covers the whole range
+			match self.interrupt.is_interrupted() && next < job.config.range.1 {
+				true => Err(Error::interrupted(job.config.range.0, next)),
+				false => Ok(()),
+			}
+		})
+	}
+}
+
+/// Rebuilds candidates of a job on the CPU and confirms them against the exact targets
+pub(crate) struct Resolver<'a> {
+	job: &'a Job,
+	stencil: solver::stencil::Stencil,
+	passphrases: Option<solver::passphrases::Passphrases>,
+	passphrase: String,
+	secp256k1: bitcoin::key::Secp256k1<bitcoin::secp256k1::All>,
+}
+
+impl<'a> Resolver<'a> {
+	pub(crate) fn new(job: &'a Job) -> Result<Resolver<'a>, Error> {
+		let config = &job.config;
+
+		Ok(Resolver {
+			job,
+			stencil: solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), job.language()).map_err(Error::Stencil)?,
+			passphrases: solver::passphrases::Passphrases::from_config(config).map_err(|err| Error::config("Passphrases", err))?,
+			passphrase: config.passphrase.clone().unwrap_or_default(),
+			secp256k1: bitcoin::key::Secp256k1::new(),
+		})
+	}
+
+	/// The match of a candidate of the job at a derivation path, `None` if it pays to none of the targets
+	pub(crate) fn resolve(&self, candidate: u64, path: &bitcoin::bip32::DerivationPath) -> Result<Option<Match>, Error> {
+		let (job, language) = (self.job, self.job.language());
+		let range = job.config.range;
+
+		// assemble mnemonic sequence, candidates failing the checksum pay to nothing
+		let (mnemonic, passphrase) = match self.passphrases.as_ref() {
+			Some(passphrases) => (self.stencil.mnemonic(0, language), passphrases.candidate(candidate)),
+			None => (self.stencil.mnemonic(candidate, language), self.passphrase.clone()),
+		};
+
+		let Ok(mnemonic) = mnemonic else {
+			return Ok(None);
+		};
+
+		// rebuild master extended key and child public key on the CPU, with the same passphrase
+		let seed = mnemonic.to_seed(&passphrase);
+		let rebuild_error = |err: bitcoin::bip32::Error| Error::Mapping(format!("Unable to rebuild the key of candidate {} at m/{}: {}", candidate, path, err));
+		let master_extended_key = bitcoin::bip32::Xpriv::new_master(job.config.network(), &seed).map_err(rebuild_error)?;
+		let child_private_key = master_extended_key.derive_priv(&self.secp256k1, path).map_err(rebuild_error)?;
+		let public_key = bitcoin::CompressedPublicKey(child_private_key.private_key.public_key(&self.secp256k1));
+
+		let Some(target) = job.targets.find(&self.secp256k1, &public_key) else {
+			return Ok(None);
+		};
+
+		// steps start at the beginning of the job's range
+		let step_size = job.step_size();
+		let step = range.0 + (candidate - range.0) / step_size * step_size;
+
+		Ok(Some(Match {
+			label: job.targets.label(&target).map(str::to_string),
+			mnemonic,
+			passphrase,
+			master_extended_key,
+			path: path.clone(),
+			target,
+			candidate,
+			range: (step, step.saturating_add(step_size).min(range.1)),
+		}))
+	}
+}
+
+/// Confirms the hits of every step against the exact targets on the CPU, reporting matches and progress.
+/// Returns the index every candidate before which was checked
+fn confirm<F: FnMut(Event)>(job: &Job, receiver: flume::Receiver<solver::StageComputation>, callback: &mut F) -> Result<u64, Error> {
+	log::debug!("Result collection thread has started");
+
+	let config = &job.config;
+
+	// track progress
+	let step_size = job.step_size();
+	let steps = job.steps();
+
+	// resolves candidates into their mnemonic, passphrase and target
+	let resolver = Resolver::new(job)?;
+
+	// hits index paths in the order they are derived
+	let paths = job.paths.iter().collect::<Vec<_>>();
+
+	// steps checked past `next`, devices finish steps out of order
+	let mut next = config.range.0;
+	let mut checked = std::collections::BTreeSet::new();
+
+	// consume messages as they arrive, until every solver thread hangs up
+	for (completed, solver::StageComputation { device, step, derived, hits }) in (1..).zip(receiver.iter()) {
+		// fingerprints matched on the device, confirm each against the exact targets
+		for hit in hits.iter() {
+			let path = &paths[hit.path as usize];
+
+			match resolver.resolve(step + hit.offset as u64, path)? {
+				Some(found) => callback(Event::Match(Box::new(found))),
+				None => log::debug!("Step = {}, Offset = {}, Path = \"m/{}\", matched a fingerprint but none of the targets", step, hit.offset, path),
+			}
+		}
+
+		// every step before `next` has been checked
+		checked.insert(step);
+		while checked.remove(&next) {
+			next = next.saturating_add(step_size).min(config.range.1);
+		}
+
+		callback(Event::Progress(Progress {
+			device,
+			next,
+			completed,
+			steps,
+			filtered: step.saturating_add(step_size).min(config.range.1) - step,
+			derived,
+		}));
+	}
+
+	Ok(next)
+}
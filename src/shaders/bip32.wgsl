@@ -0,0 +1,15 @@
+// derives the master extended key from a mnemonic seed: hmac_sha512(key = b"Bitcoin seed", data = seed)
+fn master_extended_key(seed: ptr<function, array<u32, SHA512_MAX_INPUT_SIZE>>) -> array<u32, SHA512_HASH_LENGTH> {
+    // b"Bitcoin seed" padded with 116 zeroes
+    var key = array<u32, SHA512_MAX_INPUT_SIZE>();
+    var bitcoin_seed = array<u32, 12>(66, 105, 116, 99, 111, 105, 110, 32, 115, 101, 101, 100);
+
+    for (var i = 0; i < 12; i++) {
+        key[i] = bitcoin_seed[i];
+    }
+
+    var master_extended_key: array<u32, SHA512_HASH_LENGTH>;
+    hmac_sha512(seed, SHA512_HASH_LENGTH, &key, &master_extended_key);
+
+    return master_extended_key;
+}
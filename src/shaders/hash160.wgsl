@@ -0,0 +1,181 @@
+// SHA-256 and RIPEMD-160 of short messages held as sparse bytes: public keys, redeem scripts and taproot tweaks
+
+// messages up to 119 bytes fit into two padded blocks
+const HASH_MAX_INPUT_SIZE = 128;
+
+const SHA256_K = array<u32, 64>(
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+);
+
+// word order and rotations of the left and right lines of RIPEMD-160
+const RIPEMD160_R = array<u32, 80>(
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8,
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12,
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13
+);
+
+const RIPEMD160_R_PRIME = array<u32, 80>(
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12,
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2,
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13,
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11
+);
+
+const RIPEMD160_S = array<u32, 80>(
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8,
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12,
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5,
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6
+);
+
+const RIPEMD160_S_PRIME = array<u32, 80>(
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6,
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11,
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5,
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11
+);
+
+const RIPEMD160_K = array<u32, 5>(0x00000000u, 0x5A827999u, 0x6ED9EBA1u, 0x8F1BBCDCu, 0xA953FD4Eu);
+const RIPEMD160_K_PRIME = array<u32, 5>(0x50A28BE6u, 0x5C4DD124u, 0x6D703EF3u, 0x7A6D76E9u, 0x00000000u);
+
+fn rotate_right(x: u32, n: u32) -> u32 {
+    return (x >> n) | (x << (32u - n));
+}
+
+fn rotate_left(x: u32, n: u32) -> u32 {
+    return (x << n) | (x >> (32u - n));
+}
+
+fn sha256_compress(state: ptr<function, array<u32, 8>>, block: array<u32, 16>) {
+    var w: array<u32, 64>;
+    for (var i = 0u; i < 16u; i++) {
+        w[i] = block[i];
+    }
+
+    for (var i = 16u; i < 64u; i++) {
+        let s0 = rotate_right(w[i - 15u], 7u) ^ rotate_right(w[i - 15u], 18u) ^ (w[i - 15u] >> 3u);
+        let s1 = rotate_right(w[i - 2u], 17u) ^ rotate_right(w[i - 2u], 19u) ^ (w[i - 2u] >> 10u);
+        w[i] = w[i - 16u] + s0 + w[i - 7u] + s1;
+    }
+
+    var h = *state;
+    for (var i = 0u; i < 64u; i++) {
+        let s1 = rotate_right(h[4], 6u) ^ rotate_right(h[4], 11u) ^ rotate_right(h[4], 25u);
+        let ch = (h[4] & h[5]) ^ (~h[4] & h[6]);
+        let t1 = h[7] + s1 + ch + SHA256_K[i] + w[i];
+
+        let s0 = rotate_right(h[0], 2u) ^ rotate_right(h[0], 13u) ^ rotate_right(h[0], 22u);
+        let maj = (h[0] & h[1]) ^ (h[0] & h[2]) ^ (h[1] & h[2]);
+        let t2 = s0 + maj;
+
+        h = array<u32, 8>(t1 + t2, h[0], h[1], h[2], h[3] + t1, h[4], h[5], h[6]);
+    }
+
+    for (var i = 0u; i < 8u; i++) {
+        (*state)[i] += h[i];
+    }
+}
+
+// sha256 of the first `length` bytes, as big endian words
+fn sha256_short(data: ptr<function, array<u32, HASH_MAX_INPUT_SIZE>>, length: u32) -> array<u32, 8> {
+    var state = array<u32, 8>(0x6a09e667u, 0xbb67ae85u, 0x3c6ef372u, 0xa54ff53au, 0x510e527fu, 0x9b05688cu, 0x1f83d9abu, 0x5be0cd19u);
+
+    // padding: 0x80, zeroes, then the length in bits as the last 8 bytes of the final block
+    let blocks = (length + 72u) / 64u;
+    let total = blocks * 64u;
+
+    for (var b = 0u; b < blocks; b++) {
+        // zeroed explicitly, bytes are or-ed into a fresh block every iteration
+        var block = array<u32, 16>();
+
+        for (var i = 0u; i < 64u; i++) {
+            let position = b * 64u + i;
+
+            var byte = 0u;
+            if position < length {
+                byte = data[position];
+            } else if position == length {
+                byte = 0x80u;
+            } else if position >= total - 4u {
+                byte = ((length * 8u) >> ((total - 1u - position) * 8u)) & 0xFFu;
+            }
+
+            block[i / 4u] |= byte << ((3u - i % 4u) * 8u);
+        }
+
+        sha256_compress(&state, block);
+    }
+
+    return state;
+}
+
+fn ripemd160_f(round: u32, x: u32, y: u32, z: u32) -> u32 {
+    switch round {
+        case 0u: { return x ^ y ^ z; }
+        case 1u: { return (x & y) | (~x & z); }
+        case 2u: { return (x | ~y) ^ z; }
+        case 3u: { return (x & z) | (y & ~z); }
+        default: { return x ^ (y | ~z); }
+    }
+}
+
+// ripemd160 of a sha256 digest given as big endian words, the result is big endian words of the 20 byte hash
+fn ripemd160_of_sha256(digest: array<u32, 8>) -> array<u32, 5> {
+    // ripemd160 reads little endian words: 32 bytes of message, 0x80, then the length in bits
+    var x: array<u32, 16>;
+    for (var i = 0u; i < 8u; i++) {
+        x[i] = swap_endianness(digest[i]);
+    }
+
+    x[8] = 0x80u;
+    x[14] = 256u;
+
+    var h = array<u32, 5>(0x67452301u, 0xEFCDAB89u, 0x98BADCFEu, 0x10325476u, 0xC3D2E1F0u);
+    var left = h;
+    var right = h;
+
+    for (var j = 0u; j < 80u; j++) {
+        let round = j / 16u;
+
+        let t = rotate_left(left[0] + ripemd160_f(round, left[1], left[2], left[3]) + x[RIPEMD160_R[j]] + RIPEMD160_K[round], RIPEMD160_S[j]) + left[4];
+        left = array<u32, 5>(left[4], t, left[1], rotate_left(left[2], 10u), left[3]);
+
+        let t_prime = rotate_left(right[0] + ripemd160_f(4u - round, right[1], right[2], right[3]) + x[RIPEMD160_R_PRIME[j]] + RIPEMD160_K_PRIME[round], RIPEMD160_S_PRIME[j]) + right[4];
+        right = array<u32, 5>(right[4], t_prime, right[1], rotate_left(right[2], 10u), right[3]);
+    }
+
+    let t = h[1] + left[2] + right[3];
+    h[1] = h[2] + left[3] + right[4];
+    h[2] = h[3] + left[4] + right[0];
+    h[3] = h[4] + left[0] + right[1];
+    h[4] = h[0] + left[1] + right[2];
+    h[0] = t;
+
+    var hash: array<u32, 5>;
+    for (var i = 0u; i < 5u; i++) {
+        hash[i] = swap_endianness(h[i]);
+    }
+
+    return hash;
+}
+
+fn swap_endianness(value: u32) -> u32 {
+    return (value << 24u) | ((value << 8u) & 0xFF0000u) | ((value >> 8u) & 0xFF00u) | (value >> 24u);
+}
+
+// ripemd160(sha256(data))
+fn hash160(data: ptr<function, array<u32, HASH_MAX_INPUT_SIZE>>, length: u32) -> array<u32, 5> {
+    return ripemd160_of_sha256(sha256_short(data, length));
+}
@@ -0,0 +1,92 @@
+// Keccak-256 of 64 byte messages, as used by EVM addresses. 64 bit lanes are held as (low, high) u32 pairs
+// uses swap_endianness of hash160.wgsl
+
+const KECCAK_ROUNDS = 24u;
+
+const KECCAK_ROUND_CONSTANTS = array<vec2<u32>, 24>(
+    vec2<u32>(0x00000001u, 0x00000000u), vec2<u32>(0x00008082u, 0x00000000u), vec2<u32>(0x0000808Au, 0x80000000u), vec2<u32>(0x80008000u, 0x80000000u),
+    vec2<u32>(0x0000808Bu, 0x00000000u), vec2<u32>(0x80000001u, 0x00000000u), vec2<u32>(0x80008081u, 0x80000000u), vec2<u32>(0x00008009u, 0x80000000u),
+    vec2<u32>(0x0000008Au, 0x00000000u), vec2<u32>(0x00000088u, 0x00000000u), vec2<u32>(0x80008009u, 0x00000000u), vec2<u32>(0x8000000Au, 0x00000000u),
+    vec2<u32>(0x8000808Bu, 0x00000000u), vec2<u32>(0x0000008Bu, 0x80000000u), vec2<u32>(0x00008089u, 0x80000000u), vec2<u32>(0x00008003u, 0x80000000u),
+    vec2<u32>(0x00008002u, 0x80000000u), vec2<u32>(0x00000080u, 0x80000000u), vec2<u32>(0x0000800Au, 0x00000000u), vec2<u32>(0x8000000Au, 0x80000000u),
+    vec2<u32>(0x80008081u, 0x80000000u), vec2<u32>(0x00008080u, 0x80000000u), vec2<u32>(0x80000001u, 0x00000000u), vec2<u32>(0x80008008u, 0x80000000u)
+);
+
+// rotation of each lane in the rho step, indexed by x + 5 * y
+const KECCAK_ROTATIONS = array<u32, 25>(0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14);
+
+fn rotate_lane(lane: vec2<u32>, n: u32) -> vec2<u32> {
+    // rotating by 32 or more swaps the halves first
+    var value = lane;
+    var amount = n;
+
+    if amount >= 32u {
+        value = value.yx;
+        amount -= 32u;
+    }
+
+    if amount == 0u {
+        return value;
+    }
+
+    return vec2<u32>((value.x << amount) | (value.y >> (32u - amount)), (value.y << amount) | (value.x >> (32u - amount)));
+}
+
+fn keccak_f(state: ptr<function, array<vec2<u32>, 25>>) {
+    for (var round = 0u; round < KECCAK_ROUNDS; round++) {
+        // theta
+        var columns: array<vec2<u32>, 5>;
+        for (var x = 0u; x < 5u; x++) {
+            columns[x] = state[x] ^ state[x + 5u] ^ state[x + 10u] ^ state[x + 15u] ^ state[x + 20u];
+        }
+
+        for (var x = 0u; x < 5u; x++) {
+            let d = columns[(x + 4u) % 5u] ^ rotate_lane(columns[(x + 1u) % 5u], 1u);
+            for (var y = 0u; y < 25u; y += 5u) {
+                state[x + y] ^= d;
+            }
+        }
+
+        // rho and pi: lane (x, y) moves to (y, 2x + 3y)
+        var moved: array<vec2<u32>, 25>;
+        for (var x = 0u; x < 5u; x++) {
+            for (var y = 0u; y < 5u; y++) {
+                moved[y + 5u * ((2u * x + 3u * y) % 5u)] = rotate_lane(state[x + 5u * y], KECCAK_ROTATIONS[x + 5u * y]);
+            }
+        }
+
+        // chi
+        for (var y = 0u; y < 25u; y += 5u) {
+            for (var x = 0u; x < 5u; x++) {
+                state[x + y] = moved[x + y] ^ (~moved[(x + 1u) % 5u + y] & moved[(x + 2u) % 5u + y]);
+            }
+        }
+
+        // iota
+        state[0] ^= KECCAK_ROUND_CONSTANTS[round];
+    }
+}
+
+// keccak256 of 16 big endian words, the message fits into a single block of 136 bytes
+fn keccak256_64(words: array<u32, 16>) -> array<u32, 8> {
+    var state: array<vec2<u32>, 25>;
+
+    // lanes are little endian
+    for (var i = 0u; i < 8u; i++) {
+        state[i] = vec2<u32>(swap_endianness(words[2u * i]), swap_endianness(words[2u * i + 1u]));
+    }
+
+    // padding: 0x01 after the message, 0x80 in the last byte of the block
+    state[8] = vec2<u32>(0x01u, 0u);
+    state[16] = vec2<u32>(0u, 0x80000000u);
+
+    keccak_f(&state);
+
+    var hash: array<u32, 8>;
+    for (var i = 0u; i < 4u; i++) {
+        hash[2u * i] = swap_endianness(state[i].x);
+        hash[2u * i + 1u] = swap_endianness(state[i].y);
+    }
+
+    return hash;
+}
@@ -0,0 +1,86 @@
+// up to 24 words of 11 bits each: up to 256 bits of entropy, followed by a checksum of (words / 3) bits
+const MAX_MNEMONIC_WORDS = 24;
+const ENTROPY_WORDS = 8;
+
+const WORD_LIST_SIZE = 2048u;
+
+// known words of the mnemonic, and the candidate sets of the unknown words
+struct Stencil {
+    indices: array<u32, MAX_MNEMONIC_WORDS>,
+    unknown: array<u32, MAX_MNEMONIC_WORDS>,
+    sizes: array<u32, MAX_MNEMONIC_WORDS>,
+    offsets: array<u32, MAX_MNEMONIC_WORDS>,
+    length: u32,
+    unknown_count: u32,
+    // set when the last word is unknown over the whole word list, only its entropy bits are enumerated
+    checksum: u32,
+    // word indices of unknown words restricted to part of the word list
+    candidates: array<u32>,
+};
+
+@group(0) @binding(0)
+var<storage, read> stencil: Stencil;
+
+// resolves a candidate index into word indices, as a mixed radix number whose last unknown word is least significant
+fn stencil_indices(index: u64) -> array<u32, MAX_MNEMONIC_WORDS> {
+    var indices = stencil.indices;
+    var remainder = index;
+
+    for (var i = i32(stencil.unknown_count) - 1; i >= 0; i--) {
+        let size = stencil.sizes[i];
+        let digit = u32(remainder % u64(size));
+        remainder = remainder / u64(size);
+
+        if size == WORD_LIST_SIZE {
+            indices[stencil.unknown[i]] = digit;
+        } else if stencil.checksum != 0u && i == i32(stencil.unknown_count) - 1 {
+            indices[stencil.unknown[i]] = digit << checksum_bits();
+        } else {
+            indices[stencil.unknown[i]] = stencil.candidates[stencil.offsets[i] + digit];
+        }
+    }
+
+    // checksum bits of the last word follow from the entropy
+    if stencil.checksum != 0u {
+        indices[stencil.length - 1] |= short256(indices_to_entropy(indices), entropy_bytes()) >> (8u - checksum_bits());
+    }
+
+    return indices;
+}
+
+// 12 words -> 4 bits, 24 words -> 8 bits
+fn checksum_bits() -> u32 {
+    return stencil.length / 3u;
+}
+
+// 12 words -> 16 bytes, 24 words -> 32 bytes
+fn entropy_bytes() -> u32 {
+    return stencil.length * 4u / 3u;
+}
+
+// packs 11 bit word indices into big endian u32s. Entropy always ends on a u32 boundary,
+// the checksum bits that follow it are only kept for shorter mnemonics and must be ignored past entropy_bytes()
+fn indices_to_entropy(indices: array<u32, MAX_MNEMONIC_WORDS>) -> array<u32, ENTROPY_WORDS> {
+    // one extra u32 to hold the checksum bits
+    var packed = array<u32, ENTROPY_WORDS + 1>();
+
+    for (var i = 0u; i < stencil.length; i++) {
+        let offset = i * 11u;
+        let word = offset / 32u;
+        let shift = offset % 32u;
+
+        if shift + 11u <= 32u {
+            packed[word] |= indices[i] << (21u - shift);
+        } else {
+            packed[word] |= indices[i] >> (shift - 21u);
+            packed[word + 1] |= indices[i] << (53u - shift);
+        }
+    }
+
+    var entropy = array<u32, ENTROPY_WORDS>();
+    for (var i = 0; i < ENTROPY_WORDS; i++) {
+        entropy[i] = packed[i];
+    }
+
+    return entropy;
+}
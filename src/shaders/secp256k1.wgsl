@@ -0,0 +1,290 @@
+// secp256k1 arithmetic on 256 bit integers of 8 little endian u32 limbs, limb 0 is least significant.
+// only u32 operations are used, 32 bit products are split into 16 bit halves
+
+// p = 2^256 - 2^32 - 977
+const FIELD_P = array<u32, 8>(0xFFFFFC2Fu, 0xFFFFFFFEu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu);
+// exponent of the field inverse, p - 2
+const FIELD_P_MINUS_2 = array<u32, 8>(0xFFFFFC2Du, 0xFFFFFFFEu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu);
+// 2^256 mod p = 2^32 + 977
+const FIELD_C = 977u;
+// order of the generator
+const CURVE_N = array<u32, 8>(0xD0364141u, 0xBFD25E8Cu, 0xAF48A03Bu, 0xBAAEDCE6u, 0xFFFFFFFEu, 0xFFFFFFFFu, 0xFFFFFFFFu, 0xFFFFFFFFu);
+
+// generator multiples are precomputed per 8 bit window of the scalar: table[window * 255 + digit - 1] = digit * 2^(8 * window) * G
+const GENERATOR_WINDOWS = 32u;
+const GENERATOR_DIGITS = 255u;
+
+struct AffinePoint {
+    x: array<u32, 8>,
+    y: array<u32, 8>,
+};
+
+struct JacobianPoint {
+    x: array<u32, 8>,
+    y: array<u32, 8>,
+    z: array<u32, 8>,
+    infinity: bool,
+};
+
+// full 64 bit product of two u32s, as (low, high)
+fn mul_wide(a: u32, b: u32) -> vec2<u32> {
+    let a_lo = a & 0xFFFFu;
+    let a_hi = a >> 16u;
+    let b_lo = b & 0xFFFFu;
+    let b_hi = b >> 16u;
+
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+
+    // middle terms overlap the low and high words
+    let middle = (lo_lo >> 16u) + (lo_hi & 0xFFFFu) + (hi_lo & 0xFFFFu);
+    let low = (middle << 16u) | (lo_lo & 0xFFFFu);
+    let high = hi_hi + (lo_hi >> 16u) + (hi_lo >> 16u) + (middle >> 16u);
+
+    return vec2<u32>(low, high);
+}
+
+// a >= b
+fn u256_gte(a: array<u32, 8>, b: array<u32, 8>) -> bool {
+    for (var i = 7; i >= 0; i--) {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+
+    return true;
+}
+
+fn u256_is_zero(a: array<u32, 8>) -> bool {
+    var acc = 0u;
+    for (var i = 0; i < 8; i++) {
+        acc |= a[i];
+    }
+
+    return acc == 0u;
+}
+
+// a + b, the carry is returned in limb 8
+fn u256_add(a: array<u32, 8>, b: array<u32, 8>) -> array<u32, 9> {
+    var result: array<u32, 9>;
+    var carry = 0u;
+
+    for (var i = 0; i < 8; i++) {
+        let sum = a[i] + b[i];
+        let total = sum + carry;
+        carry = select(0u, 1u, sum < a[i]) + select(0u, 1u, total < sum);
+        result[i] = total;
+    }
+
+    result[8] = carry;
+    return result;
+}
+
+// a - b, wrapping around 2^256 if b > a
+fn u256_sub(a: array<u32, 8>, b: array<u32, 8>) -> array<u32, 8> {
+    var result: array<u32, 8>;
+    var borrow = 0u;
+
+    for (var i = 0; i < 8; i++) {
+        let difference = a[i] - b[i];
+        result[i] = difference - borrow;
+        borrow = select(0u, 1u, a[i] < b[i]) + select(0u, 1u, difference < borrow);
+    }
+
+    return result;
+}
+
+fn truncate_u256(a: array<u32, 9>) -> array<u32, 8> {
+    return array<u32, 8>(a[0], a[1], a[2], a[3], a[4], a[5], a[6], a[7]);
+}
+
+// (a + b) mod m, for a, b < m
+fn add_mod(a: array<u32, 8>, b: array<u32, 8>, m: array<u32, 8>) -> array<u32, 8> {
+    let sum = u256_add(a, b);
+    let truncated = truncate_u256(sum);
+
+    if sum[8] != 0u || u256_gte(truncated, m) {
+        return u256_sub(truncated, m);
+    }
+
+    return truncated;
+}
+
+// (a - b) mod m, for a, b < m
+fn sub_mod(a: array<u32, 8>, b: array<u32, 8>, m: array<u32, 8>) -> array<u32, 8> {
+    let difference = u256_sub(a, b);
+
+    if u256_gte(a, b) {
+        return difference;
+    }
+
+    return truncate_u256(u256_add(difference, m));
+}
+
+fn fe_add(a: array<u32, 8>, b: array<u32, 8>) -> array<u32, 8> {
+    return add_mod(a, b, FIELD_P);
+}
+
+fn fe_sub(a: array<u32, 8>, b: array<u32, 8>) -> array<u32, 8> {
+    return sub_mod(a, b, FIELD_P);
+}
+
+// (a * b) mod p, the 512 bit product is folded using 2^256 = 2^32 + 977 mod p
+fn fe_mul(a: array<u32, 8>, b: array<u32, 8>) -> array<u32, 8> {
+    var product: array<u32, 16>;
+
+    for (var i = 0; i < 8; i++) {
+        var carry = 0u;
+
+        for (var j = 0; j < 8; j++) {
+            let partial = mul_wide(a[i], b[j]);
+            let sum = product[i + j] + partial.x;
+            let total = sum + carry;
+
+            carry = partial.y + select(0u, 1u, sum < partial.x) + select(0u, 1u, total < sum);
+            product[i + j] = total;
+        }
+
+        product[i + 8] = carry;
+    }
+
+    // low + high * 977 + (high << 32), at most 289 bits
+    var folded: array<u32, 10>;
+    var carry = 0u;
+
+    for (var i = 0; i < 9; i++) {
+        var high_977 = vec2<u32>(0u, 0u);
+        if i < 8 {
+            high_977 = mul_wide(product[i + 8], FIELD_C);
+        }
+
+        var low = 0u;
+        if i < 8 {
+            low = product[i];
+        }
+
+        var shifted = 0u;
+        if i > 0 {
+            shifted = product[i + 7];
+        }
+
+        // sum of four u32s, tracking every carry
+        var total = low + high_977.x;
+        var overflow = select(0u, 1u, total < low);
+
+        let with_shifted = total + shifted;
+        overflow += select(0u, 1u, with_shifted < total);
+
+        let with_carry = with_shifted + carry;
+        overflow += select(0u, 1u, with_carry < with_shifted);
+
+        folded[i] = with_carry;
+        carry = high_977.y + overflow;
+    }
+
+    folded[9] = carry;
+
+    // fold the bits above 2^256 once more: folded[8] * 2^256 = folded[8] * 977 + (folded[8] << 32)
+    var result = array<u32, 8>(folded[0], folded[1], folded[2], folded[3], folded[4], folded[5], folded[6], folded[7]);
+    let extra = mul_wide(folded[8], FIELD_C);
+
+    var addend = array<u32, 8>();
+    addend[0] = extra.x;
+    addend[1] = extra.y + folded[8];
+    addend[2] = select(0u, 1u, addend[1] < folded[8]) + folded[9];
+
+    // folded[9] is at most 1, folded[9] * 2^288 = (977 << 32) + (1 << 64)
+    if folded[9] != 0u {
+        let extra_hi = mul_wide(folded[9], FIELD_C);
+        let limb1 = addend[1] + extra_hi.x;
+        addend[2] += select(0u, 1u, limb1 < addend[1]) + extra_hi.y;
+        addend[1] = limb1;
+    }
+
+    var sum = u256_add(result, addend);
+    result = truncate_u256(sum);
+
+    // a final carry is worth 2^32 + 977, which can't overflow again
+    if sum[8] != 0u {
+        var correction = array<u32, 8>();
+        correction[0] = FIELD_C;
+        correction[1] = 1u;
+        result = truncate_u256(u256_add(result, correction));
+    }
+
+    if u256_gte(result, FIELD_P) {
+        result = u256_sub(result, FIELD_P);
+    }
+
+    return result;
+}
+
+fn fe_sqr(a: array<u32, 8>) -> array<u32, 8> {
+    return fe_mul(a, a);
+}
+
+// a^(p - 2) mod p, by Fermat's little theorem
+fn fe_inv(a: array<u32, 8>) -> array<u32, 8> {
+    var result = array<u32, 8>(1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u);
+
+    for (var i = 255; i >= 0; i--) {
+        result = fe_sqr(result);
+
+        if ((FIELD_P_MINUS_2[i / 32] >> u32(i % 32)) & 1u) != 0u {
+            result = fe_mul(result, a);
+        }
+    }
+
+    return result;
+}
+
+// jacobian + affine point, the sum of distinct window multiples of the generator is never a doubling or the point at infinity
+fn point_add_mixed(p: JacobianPoint, q: AffinePoint) -> JacobianPoint {
+    if p.infinity {
+        return JacobianPoint(q.x, q.y, array<u32, 8>(1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u), false);
+    }
+
+    let z2 = fe_sqr(p.z);
+    let z3 = fe_mul(z2, p.z);
+
+    let u2 = fe_mul(q.x, z2);
+    let s2 = fe_mul(q.y, z3);
+
+    let h = fe_sub(u2, p.x);
+    let r = fe_sub(s2, p.y);
+
+    let h2 = fe_sqr(h);
+    let h3 = fe_mul(h2, h);
+    let x1_h2 = fe_mul(p.x, h2);
+
+    let x = fe_sub(fe_sub(fe_sqr(r), h3), fe_add(x1_h2, x1_h2));
+    let y = fe_sub(fe_mul(r, fe_sub(x1_h2, x)), fe_mul(p.y, h3));
+    let z = fe_mul(p.z, h);
+
+    return JacobianPoint(x, y, z, false);
+}
+
+fn to_affine(p: JacobianPoint) -> AffinePoint {
+    let z_inv = fe_inv(p.z);
+    let z_inv2 = fe_sqr(z_inv);
+
+    return AffinePoint(fe_mul(p.x, z_inv2), fe_mul(p.y, fe_mul(z_inv2, z_inv)));
+}
+
+// scalar * G, summing one precomputed multiple per non zero byte of the scalar
+fn generator_mul(scalar: array<u32, 8>) -> AffinePoint {
+    var result: JacobianPoint;
+    result.infinity = true;
+
+    for (var window = 0u; window < GENERATOR_WINDOWS; window++) {
+        let digit = (scalar[window / 4u] >> ((window % 4u) * 8u)) & 0xFFu;
+
+        if digit != 0u {
+            result = point_add_mixed(result, generator_table[window * GENERATOR_DIGITS + digit - 1u]);
+        }
+    }
+
+    return to_affine(result);
+}
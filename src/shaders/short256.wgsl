@@ -7,10 +7,10 @@ struct SHA256_CTX {
 };
 
 const SHA256_BLOCK_SIZE = 32;
-// we consume 4 words: 32 bit integers
-const KIBBLE_COUNT = 4;
-// we hash 16 bytes at one go
-const BYTES_COUNT = 16;
+// we consume up to 8 words: 32 bit integers
+const KIBBLE_COUNT = 8;
+// we hash up to 32 bytes at one go
+const BYTES_COUNT = 32;
 
 const k = array<u32, 64>(
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
@@ -105,8 +105,8 @@ fn short256_transform(ctx: ptr<function, SHA256_CTX>) {
     (*ctx).state[7] += h;
 }
 
-fn short256_update(ctx: ptr<function, SHA256_CTX>, input: array<u32, BYTES_COUNT>) {
-    for (var i: u32 = 0; i < BYTES_COUNT; i++) {
+fn short256_update(ctx: ptr<function, SHA256_CTX>, input: array<u32, BYTES_COUNT>, len: u32) {
+    for (var i: u32 = 0; i < len; i++) {
         (*ctx).data[(*ctx).datalen] = input[i];
         (*ctx).datalen++;
 
@@ -170,9 +170,9 @@ fn short256_final(ctx: ptr<function, SHA256_CTX>) -> u32 {
 }
 
 // shortened sha256. Only returns the first 4 bytes of a normal sha256 digest
-// takes KIBBLE_COUNT "dense" 32 bit integers
-// returns 4 "sparse" bytes, for checksum
-fn short256(input: array<u32, KIBBLE_COUNT>) -> u32 {
+// takes up to KIBBLE_COUNT "dense" 32 bit integers, of which the first len bytes are hashed
+// returns the first "sparse" byte, for checksum
+fn short256(input: array<u32, KIBBLE_COUNT>, len: u32) -> u32 {
     var ctx: SHA256_CTX;
     var buf: array<u32, BYTES_COUNT>;
 
@@ -180,7 +180,7 @@ fn short256(input: array<u32, KIBBLE_COUNT>) -> u32 {
     for (var i = 0; i < KIBBLE_COUNT; i ++) {
         var temp = extract_bytes_be(input[i]);
         for (var j = 0; j < 4; j ++) {
-            buf[(i * KIBBLE_COUNT) + j] = temp[j];
+            buf[(i * 4) + j] = temp[j];
         }
     }
 
@@ -197,6 +197,6 @@ fn short256(input: array<u32, KIBBLE_COUNT>) -> u32 {
     ctx.state[6] = 0x1f83d9ab;
     ctx.state[7] = 0x5be0cd19;
 
-    short256_update(&ctx, buf);
+    short256_update(&ctx, buf, len);
     return short256_final(&ctx);
 }
@@ -0,0 +1,124 @@
+use super::*;
+use bitcoin::hashes::{hmac, sha512, Hash, HashEngine};
+
+/// Runs the filter, derivation and public key stages on every core, sending the same stream of `StageComputation`s as the GPU solver
+pub(crate) fn solve(config: &super::super::Config, targets: &targets::Targets, paths: &paths::Paths, steps: &Steps, sender: flume::Sender<StageComputation>) -> Result<(), Error> {
+	let language = stencil::language(config).expect("Language should be verified before solving");
+	let stencil = stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language).expect("Stencil should be verified before solving");
+	let passphrases = passphrases::Passphrases::from_config(config).expect("Passphrases should be verified before solving");
+	let passphrase = config.passphrase.clone().unwrap_or_default();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	let threads = std::thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+	let step_size = step_size(config) as u64;
+	log::debug!(target: "solver::cpu", "Threads = {}, StepSize = {}", threads, step_size);
+
+	while let Some(step) = steps.next() {
+		let count = (config.range.1 - step).min(step_size);
+		let chunk = count.div_ceil(threads);
+
+		// each thread handles a contiguous chunk of the step, outputs are ordered by offset
+		let chunks = std::thread::scope(|scope| {
+			let handles = (0..count)
+				.step_by(chunk as _)
+				.map(|start| {
+					let (stencil, passphrases, passphrase, secp256k1) = (&stencil, passphrases.as_ref(), passphrase.as_str(), &secp256k1);
+
+					scope.spawn(move || {
+						let offsets = start..(start + chunk).min(count);
+						let outputs = match passphrases {
+							Some(passphrases) => derive_passphrases(stencil, language, passphrases, step, offsets),
+							None => derive_mnemonics(stencil, language, passphrase, step, offsets),
+						};
+
+						let hits = find_hits(secp256k1, targets, paths, &outputs);
+						(outputs.len(), hits)
+					})
+				})
+				.collect::<Vec<_>>();
+
+			handles
+				.into_iter()
+				.map(|handle| handle.join().map_err(|_| Error::Device(format!("A CPU solver thread panicked in step {}", step))))
+				.collect::<Result<Vec<_>, _>>()
+		})?;
+
+		let derived = chunks.iter().map(|(derived, _)| derived).sum();
+		let hits = chunks.into_iter().flat_map(|(_, hits)| hits).collect::<Box<[_]>>();
+
+		log::info!(target: "solver::cpu", "Valid Mnemonic Phrases Found: {}, Hits Found: {}", derived, hits.len());
+		super::utils::send(&sender, StageComputation { device: 0, step, derived, hits })?;
+	}
+
+	Ok(())
+}
+
+/// Filters candidates of the stencil by their checksum, then derives the master extended keys of the valid ones
+fn derive_mnemonics(stencil: &stencil::Stencil, language: bip39::Language, passphrase: &str, step: u64, offsets: std::ops::Range<u64>) -> Vec<types::DerivationsOutput> {
+	let length = stencil.header.length as usize;
+
+	offsets
+		.filter_map(|offset| {
+			let indices = stencil.indices(step + offset);
+			let entropy = checksummed_entropy(&indices[..length])?;
+
+			let mnemonic = bip39::Mnemonic::from_entropy_in(language, &entropy).expect("Entropy length should be valid");
+			Some(output(offset, &mnemonic.to_seed(passphrase)))
+		})
+		.collect()
+}
+
+/// Derives the master extended keys of a complete mnemonic, salted with every candidate passphrase
+fn derive_passphrases(stencil: &stencil::Stencil, language: bip39::Language, passphrases: &passphrases::Passphrases, step: u64, offsets: std::ops::Range<u64>) -> Vec<types::DerivationsOutput> {
+	let mnemonic = stencil.mnemonic(0, language).expect("Stencil should be a complete mnemonic during passphrase recovery");
+
+	// candidates are normalized when parsed
+	offsets.map(|offset| output(offset, &mnemonic.to_seed_normalized(&passphrases.candidate(step + offset)))).collect()
+}
+
+/// Paths at which the public key of each master extended key pays to a target, ordered by offset and path
+fn find_hits<C: bitcoin::secp256k1::Signing + bitcoin::secp256k1::Verification>(
+	secp256k1: &bitcoin::secp256k1::Secp256k1<C>,
+	targets: &targets::Targets,
+	paths: &paths::Paths,
+	outputs: &[types::DerivationsOutput],
+) -> Vec<types::Hit> {
+	let mut hits = Vec::new();
+
+	for output in outputs {
+		let master = output.extended_private_key(bitcoin::Network::Bitcoin).expect("Master private keys should be valid");
+		let mut path = 0;
+
+		paths.derive(secp256k1, &master, |_, child| {
+			let public_key = bitcoin::CompressedPublicKey(child.private_key.public_key(secp256k1));
+			if targets.find(secp256k1, &public_key).is_some() {
+				hits.push(types::Hit { offset: output.offset, path });
+			}
+
+			path += 1;
+			false
+		});
+	}
+
+	hits
+}
+
+/// Packs 11 bit word indices into entropy, if the checksum in the lower bits of the last word matches. Mirrors `filter_stage.wgsl`
+fn checksummed_entropy(indices: &[u32]) -> Option<Vec<u8>> {
+	let entropy = stencil::entropy(indices);
+	let checksum_bits = indices.len() as u32 / 3;
+
+	(stencil::checksum(&entropy, checksum_bits) == indices[indices.len() - 1] & ((1 << checksum_bits) - 1)).then_some(entropy)
+}
+
+/// Master extended key of a seed, as sparse bytes
+fn output(offset: u64, seed: &[u8; 64]) -> types::DerivationsOutput {
+	let mut engine = hmac::HmacEngine::<sha512::Hash>::new(b"Bitcoin seed");
+	engine.input(seed);
+
+	let hash = hmac::Hmac::<sha512::Hash>::from_engine(engine);
+	types::DerivationsOutput {
+		offset: offset as u32,
+		hash: hash.as_byte_array().map(|b| b as u32),
+	}
+}
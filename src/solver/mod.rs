@@ -1,9 +1,14 @@
+#[cfg(debug_assertions)]
 use std::time;
 
+pub(crate) mod cpu;
 pub(crate) mod passes;
+pub(crate) mod passphrases;
+pub(crate) mod stencil;
 pub(crate) mod types;
 pub(crate) mod utils;
 
+use super::{paths, targets, Error, Interrupt};
 use passes::*;
 
 // 2 ^ 24 = 16777216
@@ -12,120 +17,208 @@ pub(crate) const STEP: u32 = 16777216; // WORKGROUP_SIZE * DISPATCH_SIZE_X * DIS
 // 6.25% chance of finding a match ~ 1398101
 pub(crate) const MAX_RESULTS_FOUND: usize = (STEP as usize) / 12;
 
+// steps in flight, each with its own matches, master extended keys and hits
+pub(crate) const DEFAULT_BUFFERS: usize = 2;
+
+// 2 ^ 20 = 1048576, every candidate passphrase is derived so a step must fit in MAX_RESULTS_FOUND.
+// So is every candidate of a stencil whose checksum is computed rather than filtered
+pub(crate) const PASSPHRASE_STEP: u32 = 1048576;
+
+/// Number of candidates processed per step
+pub(crate) fn step_size(config: &super::Config) -> u32 {
+	let checksum = stencil::language(config)
+		.and_then(|language| stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language))
+		.is_ok_and(|stencil| stencil.header.checksum != 0);
+
+	match config.passphrases.is_some() || config.mask.is_some() || checksum {
+		true => PASSPHRASE_STEP,
+		false => STEP,
+	}
+}
+
+/// Hands out the steps of a range to every device, each takes the next unclaimed step once it has a free slot.
+/// Faster devices take more steps, so none sit idle while a slower one finishes its share
+pub(crate) struct Steps {
+	range: (u64, u64),
+	size: u64,
+	/// index of the next unclaimed step
+	next: std::sync::atomic::AtomicU64,
+	stopped: std::sync::atomic::AtomicBool,
+	/// stops the steps from outside the solver, such as on Ctrl-C
+	interrupt: Interrupt,
+}
+
+impl Steps {
+	pub(crate) fn new(config: &super::Config) -> Steps {
+		Steps {
+			range: config.range,
+			size: step_size(config) as u64,
+			next: std::sync::atomic::AtomicU64::new(0),
+			stopped: std::sync::atomic::AtomicBool::new(false),
+			interrupt: Interrupt::default(),
+		}
+	}
+
+	/// Steps stopped once `interrupt` is
+	pub(crate) fn interrupted_by(mut self, interrupt: &Interrupt) -> Steps {
+		self.interrupt = interrupt.clone();
+		self
+	}
+
+	/// Claims the next step, `None` once the range is covered or the steps were stopped
+	pub(crate) fn next(&self) -> Option<u64> {
+		if self.stopped.load(std::sync::atomic::Ordering::Relaxed) || self.interrupt.is_interrupted() {
+			return None;
+		}
+
+		let index = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+		let step = index.checked_mul(self.size).and_then(|offset| offset.checked_add(self.range.0))?;
+		(step < self.range.1).then_some(step)
+	}
+
+	/// No more steps are handed out, steps already claimed are still completed
+	pub(crate) fn stop(&self) {
+		self.stopped.store(true, std::sync::atomic::Ordering::Relaxed);
+	}
+}
+
 // represents data extracted from the solver
 pub(crate) struct StageComputation {
+	/// index of the device that solved the step, always 0 on the CPU
+	pub(crate) device: usize,
 	pub(crate) step: u64,
-	pub(crate) constants: passes::derivation::Immediates,
-	pub(crate) outputs: Box<[types::DerivationsOutput]>,
+	/// number of master extended keys derived during the step
+	pub(crate) derived: usize,
+	/// candidates matching a target's fingerprint at a path, ordered by offset and path
+	pub(crate) hits: Box<[types::Hit]>,
 }
 
-pub(crate) fn solve(config: &super::Config, device: &wgpu::Device, queue: &wgpu::Queue, sender: flume::Sender<StageComputation>) {
-	// initialize passes
-	let mut filter_pass = filter::FilterPass::new(device, config.stencil.iter().map(|s| s.as_str()));
+/// Solves the steps claimed by a single device, `index` identifies the device in its `StageComputation`s
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve(
+	config: &super::Config,
+	targets: &targets::Targets,
+	paths: &paths::Paths,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	steps: &Steps,
+	index: usize,
+	sender: flume::Sender<StageComputation>,
+) -> Result<(), Error> {
+	// passphrase recovery replaces the filter and derivation stages
+	if let Some(passphrases) = passphrases::Passphrases::from_config(config).expect("Passphrases should be verified before solving") {
+		return solve_passphrases(config, targets, paths, device, queue, &passphrases, steps, index, sender);
+	}
+
+	let language = stencil::language(config).expect("Language should be verified before solving");
+	let stencil = stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language).expect("Stencil should be verified before solving");
+	let salt = types::Block::salt(config.passphrase.as_deref().unwrap_or_default()).expect("Passphrase should be verified before solving");
+
+	// initialize passes, with a set of buffers for every step in flight
+	let slots = config.buffers.unwrap_or(DEFAULT_BUFFERS);
+	let mut filter_pass = filter::FilterPass::new(device, &stencil, slots)?;
 	let reset_pass = reset::ResetPass::new(device, &filter_pass);
-	let mut derivation_pass = derivation::DerivationPass::new(device, &filter_pass);
+	let mut derivation_pass = derivation::DerivationPass::new(device, &filter_pass, &salt, language)?;
+	let master_keys = derivation_pass.slots.iter().map(|slot| &slot.output_buffer).collect::<Vec<_>>();
+	let public_key_pass = public_key::PublicKeyPass::new(device, &master_keys, paths, targets)?;
 
 	// track time taken per iteration
 	#[cfg(debug_assertions)]
 	let mut then: Option<time::Instant> = None;
 
-	// each pass steps by STEP = 2^24
-	// MAX(config.range.1) = 2^44. STEP * 2^22
-	for step in (config.range.0..config.range.1).step_by(STEP as _) {
-		// track time per iteration
-		#[cfg(debug_assertions)]
-		match then.as_mut() {
-			Some(p) => {
-				let now = time::Instant::now();
-				log::debug!("GPU Compute Passes took: {:?}", p.elapsed());
-				*p = now;
-			}
-			None => then = Some(time::Instant::now()),
-		};
+	// each pass steps by step_size, config.range is bound by the problem space of the stencil
+	// steps whose filter pass was submitted, with the slot holding their matches
+	let mut in_flight = std::collections::VecDeque::with_capacity(slots);
+	let mut next_slot = 0;
 
-		// 0: update push constants
-		let entropy = (step / STEP as u64) as u32;
-		let word1 = (filter_pass.immediates.words[1] & 0xfff00000) | entropy;
+	loop {
+		// 0: claim upcoming steps for free slots and queue their filter pass, they run while earlier steps are derived and read back
+		while in_flight.len() < slots {
+			let Some(step) = steps.next() else {
+				break;
+			};
 
-		filter_pass.immediates.words[1] = word1;
-		derivation_pass.constants.word1 = word1;
+			let slot = &filter_pass.slots[next_slot];
+			let threads = (config.range.1 - step).min(step_size(config) as _) as u32;
 
-		// 1: queue reset and filter pass
-		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("filter_pass_encoder") });
+			filter_pass.immediates.step_low = step as u32;
+			filter_pass.immediates.step_high = (step >> 32) as u32;
+			filter_pass.immediates.threads = threads;
 
-		{
-			// queue: reset pass
-			let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-				label: Some("reset_pass"),
-				timestamp_writes: None,
-			});
-
-			pass.set_pipeline(&reset_pass.pipeline);
-			pass.set_bind_group(0, &reset_pass.bind_group, &[]);
-			pass.dispatch_workgroups(reset::ResetPass::DISPATCH_SIZE_X, reset::ResetPass::DISPATCH_SIZE_Y, 1);
-		}
+			let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("filter_pass_encoder") });
 
-		{
-			// queue: filter pass
-			let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-				label: Some("filter_pass"),
-				timestamp_writes: None,
-			});
+			{
+				// queue: reset pass
+				let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+					label: Some("reset_pass"),
+					timestamp_writes: None,
+				});
+
+				pass.set_pipeline(&reset_pass.pipeline);
+				pass.set_bind_group(0, &reset_pass.bind_groups[next_slot], &[]);
+				pass.dispatch_workgroups(reset::ResetPass::DISPATCH_SIZE_X, reset::ResetPass::DISPATCH_SIZE_Y, 1);
+			}
 
-			pass.set_pipeline(&filter_pass.pipeline);
-			pass.set_immediates(0, bytemuck::cast_slice(&[filter_pass.immediates]));
-			pass.set_bind_group(0, &filter_pass.bind_group, &[]);
+			{
+				// queue: filter pass
+				let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+					label: Some("filter_pass"),
+					timestamp_writes: None,
+				});
 
-			// calculate dimensions of dispatch
-			let threads = (config.range.1 - step).min(STEP as _);
-			let dispatch = (threads as u32).div_ceil(filter::FilterPass::WORKGROUP_SIZE);
+				pass.set_pipeline(&filter_pass.pipeline);
+				pass.set_immediates(0, bytemuck::cast_slice(&[filter_pass.immediates]));
+				pass.set_bind_group(0, &slot.bind_group, &[]);
 
-			let dispatch_x = filter::FilterPass::DISPATCH_SIZE_X.min(dispatch);
-			let dispatch_y = (dispatch / filter::FilterPass::DISPATCH_SIZE_Y).max(1);
+				// calculate dimensions of dispatch
+				let dispatch = threads.div_ceil(filter::FilterPass::WORKGROUP_SIZE);
 
-			log::debug!(target: "solver::filter_stage", "Threads = {}, DispatchX = {}, DispatchY = {}, WorkgroupSize = {}", threads, dispatch_x, dispatch_y, filter::FilterPass::WORKGROUP_SIZE);
-			pass.dispatch_workgroups(dispatch_x, dispatch_y, 1);
-		}
+				let dispatch_x = filter::FilterPass::DISPATCH_SIZE_X.min(dispatch);
+				let dispatch_y = dispatch.div_ceil(filter::FilterPass::DISPATCH_SIZE_X).min(filter::FilterPass::DISPATCH_SIZE_Y);
 
-		{
-			// queue: copy GPU output buffers to CPU mapped buffers
-			encoder.copy_buffer_to_buffer(&filter_pass.count_buffer, 0, &filter_pass.count_buffer_dest, 0, filter_pass.count_buffer.size());
-		}
+				log::debug!(target: "solver::filter_stage", "Threads = {}, DispatchX = {}, DispatchY = {}, WorkgroupSize = {}", threads, dispatch_x, dispatch_y, filter::FilterPass::WORKGROUP_SIZE);
+				pass.dispatch_workgroups(dispatch_x, dispatch_y, 1);
+			}
 
-		// submit
-		queue.submit([encoder.finish()]);
-		device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
+			{
+				// queue: copy GPU output buffers to CPU mapped buffers
+				encoder.copy_buffer_to_buffer(&slot.count_buffer, 0, &slot.count_buffer_dest, 0, slot.count_buffer.size());
+			}
 
-		// 2: read X matches produced by filter stage
-		let matches_count = {
-			let (count_send, count_recv) = flume::bounded(1);
-			let _count_buffer = filter_pass.count_buffer_dest.clone();
+			// submit, without waiting for the device
+			let submission = queue.submit([encoder.finish()]);
+			in_flight.push_back((step, next_slot, submission));
+			next_slot = (next_slot + 1) % slots;
+		}
 
-			// read count buffer
-			filter_pass.count_buffer_dest.map_async(wgpu::MapMode::Read, .., move |res| match res {
-				Ok(_) => {
-					let range = _count_buffer.get_mapped_range(..);
-					let bytes: &[u32] = bytemuck::cast_slice(range.as_ref());
+		// 1: derive the oldest step in flight, every step has been read back once none are left
+		let Some((step, slot, submission)) = in_flight.pop_front() else {
+			break;
+		};
 
-					count_send.send(bytes[0]).unwrap();
+		// track time per iteration
+		#[cfg(debug_assertions)]
+		match then.as_mut() {
+			Some(p) => {
+				let now = time::Instant::now();
+				log::debug!("GPU Compute Passes took: {:?}", p.elapsed());
+				*p = now;
+			}
+			None => then = Some(time::Instant::now()),
+		};
 
-					drop(range);
-					_count_buffer.unmap();
-				}
-				Err(err) => {
-					log::error!("Unable to reset count buffer: {}", err);
-				}
-			});
+		derivation_pass.constants.step_low = step as u32;
+		derivation_pass.constants.step_high = (step >> 32) as u32;
 
-			// poll map_async callback
-			device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
-			let count = count_recv.recv_timeout(time::Duration::from_secs(5)).expect("Unable to acquire matches_count from buffer");
+		// 2: read X matches produced by filter stage, later filter passes keep running
+		let matches_count = {
+			let count = utils::inspect_buffer::<u32, _, _>(device, &filter_pass.slots[slot].count_buffer_dest, submission, |count| count[0])?;
 
 			log::info!(target: "solver::filter_stage", "Valid Mnemonic Phrases Found: {}", count);
 
 			// output buffer was full
 			if count >= MAX_RESULTS_FOUND as _ {
-				panic!("More than {} results found: {}", MAX_RESULTS_FOUND, count);
+				return Err(Error::Mapping(format!("More than {} results found in step {}: {}", MAX_RESULTS_FOUND, step, count)));
 			};
 
 			count
@@ -134,8 +227,9 @@ pub(crate) fn solve(config: &super::Config, device: &wgpu::Device, queue: &wgpu:
 		// 3: queue derivations passes
 
 		{
-			// call derivations pass in smaller dispatches to avoid GPU timeouts
+			// call derivations pass in smaller dispatches to avoid GPU timeouts, the public key pass is queued behind the last one
 			let mut constants = derivation_pass.constants;
+			let mut previous = None;
 			constants.count = matches_count;
 
 			let dispatch = config.dispatch.unwrap_or(64);
@@ -160,56 +254,118 @@ pub(crate) fn solve(config: &super::Config, device: &wgpu::Device, queue: &wgpu:
 					});
 
 					pass.set_pipeline(&derivation_pass.pipeline);
-					pass.set_bind_group(0, &derivation_pass.bind_group, &[]);
+					pass.set_bind_group(0, &derivation_pass.slots[slot].bind_group, &[]);
 
 					pass.set_immediates(0, bytemuck::cast_slice(&[constants]));
 					pass.dispatch_workgroups(dispatch, 1, 1);
 				}
 
-				// copy data from output_buffer to output_buffer_dest
-				encoder.copy_buffer_to_buffer(
-					&derivation_pass.output_buffer,
-					0,
-					&derivation_pass.output_buffer_dest,
-					0,
-					(matches_count as usize * std::mem::size_of::<types::DerivationsOutput>()) as wgpu::BufferAddress,
-				);
-
-				// submit
-				queue.submit([encoder.finish()]);
-				device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
+				// submit, waiting on the chunk before so the next one is always queued behind the running one
+				let submission = queue.submit([encoder.finish()]);
+				if let Some(previous) = previous.replace(submission) {
+					utils::wait(device, previous)?;
+				}
 
 				// are we done?
 				constants.offset = constants.offset.saturating_add(threads);
 			}
 		}
 
-		{
-			// 4: send copies of compute work over sender
-			let hashes_src_ = derivation_pass.output_buffer_dest.clone();
-			let sender_ = sender.clone();
+		// 4: match public keys at every path against the targets, master extended keys stay on the GPU
+		let hits = public_key_pass.find(device, queue, slot, matches_count, config.dispatch.unwrap_or(64))?;
 
-			derivation_pass.output_buffer_dest.map_async(wgpu::MapMode::Read, .., move |res| {
-				res.unwrap();
+		// 5: send hits over sender
+		let output = StageComputation {
+			device: index,
+			step,
+			derived: matches_count as usize,
+			hits,
+		};
 
-				let range = hashes_src_.get_mapped_range(..);
-				let results: &[types::DerivationsOutput] = bytemuck::cast_slice(range.as_ref());
+		utils::send(&sender, output)?;
+	}
 
-				let output = StageComputation {
-					step,
-					constants: derivation_pass.constants,
-					outputs: Box::from(&results[..matches_count as _]),
-				};
+	Ok(())
+}
 
-				// send results
-				sender_.send(output).expect("Unable to send results through channel");
+/// Derives the master extended keys of a complete mnemonic, salted with every candidate passphrase
+#[allow(clippy::too_many_arguments)]
+fn solve_passphrases(
+	config: &super::Config,
+	targets: &targets::Targets,
+	paths: &paths::Paths,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	passphrases: &passphrases::Passphrases,
+	steps: &Steps,
+	index: usize,
+	sender: flume::Sender<StageComputation>,
+) -> Result<(), Error> {
+	let language = stencil::language(config).expect("Language should be verified before solving");
+	let stencil = stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language).expect("Stencil should be verified before solving");
+	let mnemonic = stencil.mnemonic(0, language).expect("Stencil should be a complete mnemonic during passphrase recovery");
+
+	let passphrase_pass = passphrase::PassphrasePass::new(device, &types::Block::password(&mnemonic), passphrases)?;
+	let public_key_pass = public_key::PublicKeyPass::new(device, &[&passphrase_pass.output_buffer], paths, targets)?;
+
+	while let Some(step) = steps.next() {
+		let count = (config.range.1 - step).min(PASSPHRASE_STEP as _) as u32;
+
+		// 0: update push constants and candidates
+		passphrase_pass.upload(queue, passphrases, step, count);
+
+		let mut constants = passphrase_pass.constants;
+		constants.step_low = step as u32;
+		constants.step_high = (step >> 32) as u32;
+		constants.count = count;
+
+		// 1: call passphrase pass in smaller dispatches to avoid GPU timeouts, the public key pass is queued behind the last one
+		let mut previous = None;
+		let dispatch = config.dispatch.unwrap_or(64);
+		let max_threads = dispatch * passphrase::PassphrasePass::WORKGROUP_SIZE;
+		log::debug!(target: "solver::passphrase_stage", "Candidates = {}, Config.Dispatch = {}, WorkgroupSize = {}", count, dispatch, passphrase::PassphrasePass::WORKGROUP_SIZE);
+
+		while constants.offset < count {
+			let threads = (count - constants.offset).min(max_threads);
+			let dispatch = threads.div_ceil(passphrase::PassphrasePass::WORKGROUP_SIZE);
+
+			let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("passphrase_pass_encoder") });
+
+			{
+				let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+					label: Some("passphrase_pass"),
+					timestamp_writes: None,
+				});
 
-				drop(range);
-				hashes_src_.unmap();
-			});
+				pass.set_pipeline(&passphrase_pass.pipeline);
+				pass.set_bind_group(0, &passphrase_pass.bind_group, &[]);
 
-			// poll map_async callback
-			device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
+				pass.set_immediates(0, bytemuck::cast_slice(&[constants]));
+				pass.dispatch_workgroups(dispatch, 1, 1);
+			}
+
+			// submit, waiting on the chunk before so the next one is always queued behind the running one
+			let submission = queue.submit([encoder.finish()]);
+			if let Some(previous) = previous.replace(submission) {
+				utils::wait(device, previous)?;
+			}
+
+			constants.offset = constants.offset.saturating_add(threads);
 		}
+
+		// 2: match public keys at every path against the targets
+		let hits = public_key_pass.find(device, queue, 0, count, dispatch)?;
+
+		// 3: send hits over sender
+		let output = StageComputation {
+			device: index,
+			step,
+			derived: count as usize,
+			hits,
+		};
+
+		utils::send(&sender, output)?;
 	}
+
+	Ok(())
 }
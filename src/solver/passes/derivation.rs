@@ -4,49 +4,44 @@ use wgpu::util::DeviceExt;
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct Immediates {
-	pub(crate) word0: u32,
-	pub(crate) word1: u32,
-	pub(crate) word3: u32,
-	pub(crate) checksum: u32,
+	pub(crate) step_low: u32,
+	pub(crate) step_high: u32,
 	pub(crate) offset: u32,
 	pub(crate) count: u32,
 }
 
+/// Master extended keys of the matches in the filter slot of the same index
+pub(crate) struct DerivationSlot {
+	pub bind_group: wgpu::BindGroup,
+	pub output_buffer: wgpu::Buffer,
+}
+
 pub(crate) struct DerivationPass {
 	pub constants: Immediates,
 	pub pipeline: wgpu::ComputePipeline,
-	pub bind_group: wgpu::BindGroup,
-	pub output_buffer: wgpu::Buffer,
-	pub output_buffer_dest: wgpu::Buffer,
+	pub slots: Vec<DerivationSlot>,
 }
 
 impl DerivationPass {
+	/// WGSL has no imports, shared modules are concatenated
+	pub(crate) const SOURCE: &str = concat!(
+		include_str!("../../shaders/sha512.wgsl"),
+		include_str!("../../shaders/pbkdf2.wgsl"),
+		include_str!("../../shaders/bip32.wgsl"),
+		include_str!("../../shaders/short256.wgsl"),
+		include_str!("../../shaders/mnemonic.wgsl"),
+		include_str!("derivation_stage.wgsl")
+	);
+
 	pub(crate) const WORKGROUP_SIZE: u32 = 256;
 
-	pub(crate) fn new(device: &wgpu::Device, filter_pass: &filter::FilterPass) -> DerivationPass {
-		assert!(
-			std::mem::size_of::<Immediates>() as u32 <= device.limits().max_immediate_size,
-			"filter::PushConstants too large for device, unable to init pipeline"
-		);
+	pub(crate) fn new(device: &wgpu::Device, filter_pass: &filter::FilterPass, salt: &types::Block, language: bip39::Language) -> Result<DerivationPass, Error> {
+		utils::check_immediates::<Immediates>(device, "derivation")?;
 
 		// prepare buffers
 		let word_list_buffer = {
 			// allocate word list buffer
-			let words = bip39::Language::English
-				.word_list()
-				.iter()
-				.map(|word| {
-					let bytes = word.as_bytes();
-
-					let mut buffer = [0u8; 8];
-					buffer[..bytes.len()].copy_from_slice(bytes);
-
-					types::Bip39Word {
-						bytes: buffer.map(|s| s as u32),
-						length: bytes.len() as u32,
-					}
-				})
-				.collect::<Vec<_>>();
+			let words = language.word_list().iter().map(|word| types::Bip39Word::new(word)).collect::<Vec<_>>();
 
 			let descriptor = wgpu::util::BufferInitDescriptor {
 				label: Some("derivation_word_list"),
@@ -57,25 +52,16 @@ impl DerivationPass {
 			device.create_buffer_init(&descriptor)
 		};
 
-		let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("derivation_outputs"),
-			size: (std::mem::size_of::<[types::DerivationsOutput; MAX_RESULTS_FOUND]>() as usize) as wgpu::BufferAddress,
-			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-			mapped_at_creation: false,
-		});
-
-		let output_buffer_dest = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("derivation_outputs"),
-			size: (std::mem::size_of::<[types::DerivationsOutput; MAX_RESULTS_FOUND]>() as usize) as wgpu::BufferAddress,
-			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-			mapped_at_creation: false,
+		let salt_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("derivation_salt"),
+			contents: bytemuck::cast_slice(&[*salt]),
+			usage: wgpu::BufferUsages::STORAGE,
 		});
 
 		// compile shader
-		let source = concat!(include_str!("../../shaders/sha512.wgsl"), include_str!("../../shaders/pbkdf2.wgsl"), include_str!("derivation_stage.wgsl"));
 		let descriptor = wgpu::ShaderModuleDescriptor {
 			label: Some("derivation_main"),
-			source: wgpu::ShaderSource::Wgsl(source.into()),
+			source: wgpu::ShaderSource::Wgsl(Self::SOURCE.into()),
 		};
 
 		let shader = device.create_shader_module(descriptor);
@@ -84,6 +70,16 @@ impl DerivationPass {
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("derivation_bind_group_layout"),
 			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: true },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
 				wgpu::BindGroupLayoutEntry {
 					binding: 1,
 					visibility: wgpu::ShaderStages::COMPUTE,
@@ -114,25 +110,15 @@ impl DerivationPass {
 					},
 					count: None,
 				},
-			],
-		});
-
-		// configure bind groups
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("derivation_bind_group"),
-			layout: &bind_group_layout,
-			entries: &[
-				wgpu::BindGroupEntry {
-					binding: 1,
-					resource: filter_pass.matches_buffer.as_entire_binding(),
-				},
-				wgpu::BindGroupEntry {
-					binding: 2,
-					resource: word_list_buffer.as_entire_binding(),
-				},
-				wgpu::BindGroupEntry {
-					binding: 3,
-					resource: output_buffer.as_entire_binding(),
+				wgpu::BindGroupLayoutEntry {
+					binding: 4,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: true },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
 				},
 			],
 		});
@@ -155,19 +141,54 @@ impl DerivationPass {
 			compilation_options: Default::default(),
 		});
 
-		DerivationPass {
+		// outputs of every filter slot
+		let slots = filter_pass
+			.slots
+			.iter()
+			.map(|filter_slot| {
+				let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("derivation_outputs"),
+					size: (std::mem::size_of::<[types::DerivationsOutput; MAX_RESULTS_FOUND]>() as usize) as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+					mapped_at_creation: false,
+				});
+
+				// configure bind group
+				let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("derivation_bind_group"),
+					layout: &bind_group_layout,
+					entries: &[
+						wgpu::BindGroupEntry {
+							binding: 0,
+							resource: filter_pass.stencil_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 1,
+							resource: filter_slot.matches_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 2,
+							resource: word_list_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 3,
+							resource: output_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 4,
+							resource: salt_buffer.as_entire_binding(),
+						},
+					],
+				});
+
+				DerivationSlot { bind_group, output_buffer }
+			})
+			.collect();
+
+		Ok(DerivationPass {
 			pipeline,
-			bind_group,
-			output_buffer,
-			output_buffer_dest,
-			constants: Immediates {
-				checksum: filter_pass.immediates.checksum,
-				offset: 0,
-				count: 0,
-				word0: filter_pass.immediates.words[0],
-				word1: 0,
-				word3: filter_pass.immediates.words[3],
-			},
-		}
+			slots,
+			constants: bytemuck::Zeroable::zeroed(),
+		})
 	}
 }
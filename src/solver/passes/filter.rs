@@ -4,75 +4,48 @@ use wgpu::util::DeviceExt;
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct Immediates {
-	pub(crate) words: [u32; 4],
-	pub(crate) checksum: u32,
+	pub(crate) step_low: u32,
+	pub(crate) step_high: u32,
+	pub(crate) threads: u32,
 }
 
-impl Immediates {
-	pub(crate) fn from_stencil<'a, I: Iterator<Item = &'a str>>(words: I) -> Immediates {
-		// map stencil to mnemonic
-		let replaced = words.map(|s| if s == "_" { "abandon" } else { s }).collect::<Vec<_>>().join(" ");
-		let mnemonic = bip39::Mnemonic::parse_in_normalized_without_checksum_check(bip39::Language::English, &replaced).unwrap();
-
-		let entropy = mnemonic.to_entropy();
-
-		// mnemonic is bigEndian: 💀
-		let mut words = [0u32; 4];
-		words.copy_from_slice(bytemuck::cast_slice(&entropy));
-		words = words.map(|w| w.to_be()); // TODO: does this make sense?
-
-		Immediates {
-			words,
-			checksum: mnemonic.checksum() as _,
-		}
-	}
+/// Buffers of a single step in flight, steps cycle through the sets so the filter of a step is queued while an earlier one is derived
+pub(crate) struct FilterSlot {
+	pub bind_group: wgpu::BindGroup,
+	pub matches_buffer: wgpu::Buffer,
+	pub count_buffer: wgpu::Buffer,
+	pub count_buffer_dest: wgpu::Buffer,
 }
 
 pub(crate) struct FilterPass {
 	pub immediates: Immediates,
+	pub stencil_buffer: wgpu::Buffer,
 	pub pipeline: wgpu::ComputePipeline,
-	pub bind_group: wgpu::BindGroup,
-	pub matches_buffer: wgpu::Buffer,
-	pub count_buffer: wgpu::Buffer,
-	pub count_buffer_dest: wgpu::Buffer,
+	pub slots: Vec<FilterSlot>,
 }
 
 impl FilterPass {
+	/// WGSL has no imports, shared modules are concatenated
+	pub(crate) const SOURCE: &str = concat!(include_str!("../../shaders/short256.wgsl"), include_str!("../../shaders/mnemonic.wgsl"), include_str!("filter_stage.wgsl"));
+
 	pub(crate) const WORKGROUP_SIZE: u32 = 256; // 2 ^ 8
 	pub(crate) const DISPATCH_SIZE_X: u32 = 256; // 2 ^ 8
 	pub(crate) const DISPATCH_SIZE_Y: u32 = 256; // 2 ^ 8
 
-	pub(crate) fn new<'a, I: Iterator<Item = &'a str>>(device: &wgpu::Device, stencil: I) -> FilterPass {
-		assert!(
-			std::mem::size_of::<Immediates>() as u32 <= device.limits().max_immediate_size,
-			"filter::PushConstants too large for device, unable to init pipeline"
-		);
+	pub(crate) fn new(device: &wgpu::Device, stencil: &stencil::Stencil, slots: usize) -> Result<FilterPass, Error> {
+		utils::check_immediates::<Immediates>(device, "filter")?;
 
 		// prepare buffers
-		let count_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-			label: Some("solver_count"),
-			contents: bytemuck::cast_slice(&[0]),
-			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-		});
-
-		let count_buffer_dest = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-			label: Some("solver_count_dest"),
-			contents: bytemuck::cast_slice(&[0]),
-			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-		});
-
-		let matches_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label: Some("solver_matches"),
-			size: (std::mem::size_of::<[types::Word; MAX_RESULTS_FOUND]>() as usize) as wgpu::BufferAddress,
+		let stencil_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("solver_stencil"),
+			contents: &stencil.to_bytes(),
 			usage: wgpu::BufferUsages::STORAGE,
-			mapped_at_creation: false,
 		});
 
 		// compile shader
-		let source = concat!(include_str!("../../shaders/short256.wgsl"), include_str!("filter_stage.wgsl"));
 		let descriptor = wgpu::ShaderModuleDescriptor {
 			label: Some("filter_main"),
-			source: wgpu::ShaderSource::Wgsl(source.into()),
+			source: wgpu::ShaderSource::Wgsl(Self::SOURCE.into()),
 		};
 
 		#[cfg(debug_assertions)]
@@ -84,6 +57,16 @@ impl FilterPass {
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("filter_bind_group_layout"),
 			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::COMPUTE,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Storage { read_only: true },
+						has_dynamic_offset: false,
+						min_binding_size: None,
+					},
+					count: None,
+				},
 				wgpu::BindGroupLayoutEntry {
 					binding: 1,
 					visibility: wgpu::ShaderStages::COMPUTE,
@@ -107,22 +90,6 @@ impl FilterPass {
 			],
 		});
 
-		// configure bind groups
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("filter_bind_group"),
-			layout: &bind_group_layout,
-			entries: &[
-				wgpu::BindGroupEntry {
-					binding: 1,
-					resource: count_buffer.as_entire_binding(),
-				},
-				wgpu::BindGroupEntry {
-					binding: 2,
-					resource: matches_buffer.as_entire_binding(),
-				},
-			],
-		});
-
 		// configure pipeline layout
 		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 			label: Some("filter_pipeline_layout"),
@@ -141,13 +108,62 @@ impl FilterPass {
 			compilation_options: Default::default(),
 		});
 
-		FilterPass {
+		// buffers of every step in flight
+		let slots = (0..slots)
+			.map(|_| {
+				let count_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("solver_count"),
+					contents: bytemuck::cast_slice(&[0]),
+					usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+				});
+
+				let count_buffer_dest = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("solver_count_dest"),
+					contents: bytemuck::cast_slice(&[0]),
+					usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+				});
+
+				let matches_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("solver_matches"),
+					size: (std::mem::size_of::<[types::Offset; MAX_RESULTS_FOUND]>() as usize) as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::STORAGE,
+					mapped_at_creation: false,
+				});
+
+				// configure bind group
+				let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("filter_bind_group"),
+					layout: &bind_group_layout,
+					entries: &[
+						wgpu::BindGroupEntry {
+							binding: 0,
+							resource: stencil_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 1,
+							resource: count_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 2,
+							resource: matches_buffer.as_entire_binding(),
+						},
+					],
+				});
+
+				FilterSlot {
+					bind_group,
+					matches_buffer,
+					count_buffer,
+					count_buffer_dest,
+				}
+			})
+			.collect();
+
+		Ok(FilterPass {
 			pipeline,
-			bind_group,
-			matches_buffer,
-			count_buffer,
-			count_buffer_dest,
-			immediates: Immediates::from_stencil(stencil),
-		}
+			stencil_buffer,
+			slots,
+			immediates: bytemuck::Zeroable::zeroed(),
+		})
 	}
 }
@@ -6,11 +6,9 @@ const DISPATCH_SIZE_Y = 256; // 2 ^ 8
 const MAX_RESULTS_FOUND = 1398101;
 
 struct Immediates {
-    word0: u32,
-    word1: u32,
-    word2_partial: u32,
-    word3: u32,
-    checksum: u32,
+    step_low: u32,
+    step_high: u32,
+    threads: u32,
 };
 
 var<immediate> constants: Immediates;
@@ -24,27 +22,32 @@ var<storage, read_write> matches: array<u32, MAX_RESULTS_FOUND>;
 // TODO: Compress cryptographic functions from sparse to dense u32s
 
 // workgroups: (2 ^ 8, 1, 1) rectangles, basically 1D
-// dispatch: (2 ^ 8, 2 ^ 8, 1), but we index into the space depending on the offset
+// dispatch: (2 ^ 8, 2 ^ 8, 1), threads are linearly indexed relative to the current step
 @compute @workgroup_size(WORKGROUP_SIZE)
 fn main(
     @builtin(local_invocation_id) local: vec3<u32>,
-    @builtin(workgroup_id) workgroup_id: vec3<u32>
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
 ) {
-    if atomicLoad(&count) >= MAX_RESULTS_FOUND {
+    let offset = (workgroup_id.y * num_workgroups.x + workgroup_id.x) * WORKGROUP_SIZE + local.x;
+    if offset >= constants.threads || atomicLoad(&count) >= MAX_RESULTS_FOUND {
         return;
     }
 
-    // word[2]: upper 24 bits of entropy come from global index, lower 8 bits are known
-    var entropy_2 = (local.x << 16) | (workgroup_id.y << 8) | workgroup_id.x;
-    var word_2 = constants.word2_partial | (entropy_2 << 8);
+    // resolve candidate from stencil
+    let step = (u64(constants.step_high) << 32u) | u64(constants.step_low);
+    var indices = stencil_indices(step + u64(offset));
 
-    // verify mnemonic checksum
-    var entropy = array<u32, 4>(constants.word0, constants.word1, word_2, constants.word3);
-    var short256 = short256(entropy);
+    // verify mnemonic checksum, stored in the lower bits of the last word
+    var entropy = indices_to_entropy(indices);
+    var short256 = short256(entropy, entropy_bytes());
 
     // if entropy matches, queue for next stage
-    if short256 >> 4 == constants.checksum {
+    let checksum_bits = checksum_bits();
+    if short256 >> (8u - checksum_bits) == (indices[stencil.length - 1] & ((1u << checksum_bits) - 1u)) {
         var index = atomicAdd(&count, 1u);
-        matches[index] = word_2;
+        if index < MAX_RESULTS_FOUND {
+            matches[index] = offset;
+        }
     }
 }
@@ -1,3 +1,5 @@
 pub(crate) mod derivation;
 pub(crate) mod filter;
+pub(crate) mod passphrase;
+pub(crate) mod public_key;
 pub(crate) mod reset;
@@ -0,0 +1,226 @@
+use super::super::*;
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Immediates {
+	pub(crate) step_low: u32,
+	pub(crate) step_high: u32,
+	pub(crate) offset: u32,
+	pub(crate) count: u32,
+	pub(crate) mode: u32,
+}
+
+/// Charsets of a mask, as laid out in `passphrase_stage.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Mask {
+	pub(crate) length: u32,
+	pub(crate) offsets: [u32; passphrases::MAX_MASK_LENGTH],
+	pub(crate) sizes: [u32; passphrases::MAX_MASK_LENGTH],
+}
+
+/// Replaces the filter and derivation passes during passphrase recovery: every thread derives the
+/// master extended key of the same mnemonic, salted with a different candidate passphrase
+pub(crate) struct PassphrasePass {
+	pub constants: Immediates,
+	pub pipeline: wgpu::ComputePipeline,
+	pub bind_group: wgpu::BindGroup,
+	pub words_buffer: wgpu::Buffer,
+	pub word_offsets_buffer: wgpu::Buffer,
+	pub output_buffer: wgpu::Buffer,
+}
+
+impl PassphrasePass {
+	/// WGSL has no imports, shared modules are concatenated
+	pub(crate) const SOURCE: &str = concat!(
+		include_str!("../../shaders/sha512.wgsl"),
+		include_str!("../../shaders/pbkdf2.wgsl"),
+		include_str!("../../shaders/bip32.wgsl"),
+		include_str!("passphrase_stage.wgsl")
+	);
+
+	pub(crate) const WORKGROUP_SIZE: u32 = 256;
+
+	pub(crate) const MODE_WORDLIST: u32 = 0;
+	pub(crate) const MODE_MASK: u32 = 1;
+
+	pub(crate) fn new(device: &wgpu::Device, password: &types::Block, passphrases: &passphrases::Passphrases) -> Result<PassphrasePass, Error> {
+		utils::check_immediates::<Immediates>(device, "passphrase")?;
+
+		// prepare buffers
+		let password_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("passphrase_password"),
+			contents: bytemuck::cast_slice(&[*password]),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		// masks are generated on the GPU, wordlists are uploaded per step
+		let mut mask: Mask = bytemuck::Zeroable::zeroed();
+		let mut charsets = vec![0u32];
+		let mut words_size = 1;
+
+		match passphrases {
+			passphrases::Passphrases::Mask(positions) => {
+				charsets.clear();
+				mask.length = positions.len() as u32;
+
+				for (idx, charset) in positions.iter().enumerate() {
+					mask.offsets[idx] = charsets.len() as u32;
+					mask.sizes[idx] = charset.len() as u32;
+					charsets.extend(charset.iter().map(|&b| b as u32));
+				}
+			}
+			passphrases::Passphrases::Wordlist(words) => {
+				// steps are not aligned to chunks, but always lie within two adjacent chunks
+				let sums = words.chunks(PASSPHRASE_STEP as usize).map(|chunk| chunk.iter().map(|w| w.len()).sum::<usize>()).collect::<Vec<_>>();
+				let largest = sums.windows(2).map(|pair| pair[0] + pair[1]).chain(sums.first().copied()).max();
+
+				words_size = largest.unwrap_or_default().max(1);
+			}
+		}
+
+		let mask_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("passphrase_mask"),
+			contents: bytemuck::cast_slice(&[mask]),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		let charsets_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("passphrase_charsets"),
+			contents: bytemuck::cast_slice(&charsets),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		let words_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("passphrase_words"),
+			size: (words_size * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let word_offsets_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("passphrase_word_offsets"),
+			size: ((PASSPHRASE_STEP as usize + 1) * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("passphrase_outputs"),
+			size: std::mem::size_of::<[types::DerivationsOutput; MAX_RESULTS_FOUND]>() as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+
+		// compile shader
+		let descriptor = wgpu::ShaderModuleDescriptor {
+			label: Some("passphrase_main"),
+			source: wgpu::ShaderSource::Wgsl(Self::SOURCE.into()),
+		};
+
+		let shader = device.create_shader_module(descriptor);
+
+		// configure bind group layout
+		let entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::COMPUTE,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Storage { read_only },
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		};
+
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("passphrase_bind_group_layout"),
+			entries: &[entry(0, true), entry(1, true), entry(2, true), entry(3, true), entry(4, true), entry(5, false)],
+		});
+
+		// configure bind groups
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("passphrase_bind_group"),
+			layout: &bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: password_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: mask_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: charsets_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 3,
+					resource: words_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 4,
+					resource: word_offsets_buffer.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 5,
+					resource: output_buffer.as_entire_binding(),
+				},
+			],
+		});
+
+		// configure pipeline layout
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("passphrase_pipeline_layout"),
+			bind_group_layouts: &[Some(&bind_group_layout)],
+			immediate_size: std::mem::size_of::<Immediates>() as u32,
+		});
+
+		// create compute pipeline
+		let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+			label: Some("passphrase_pipeline"),
+			module: &shader,
+			entry_point: Some("main"),
+			layout: Some(&pipeline_layout),
+			// defaults
+			cache: None,
+			compilation_options: Default::default(),
+		});
+
+		let mode = match passphrases {
+			passphrases::Passphrases::Wordlist(_) => Self::MODE_WORDLIST,
+			passphrases::Passphrases::Mask(_) => Self::MODE_MASK,
+		};
+
+		Ok(PassphrasePass {
+			pipeline,
+			bind_group,
+			words_buffer,
+			word_offsets_buffer,
+			output_buffer,
+			constants: Immediates { mode, ..bytemuck::Zeroable::zeroed() },
+		})
+	}
+
+	/// Uploads the wordlist candidates of a step, starting at `step`
+	pub(crate) fn upload(&self, queue: &wgpu::Queue, passphrases: &passphrases::Passphrases, step: u64, count: u32) {
+		let passphrases::Passphrases::Wordlist(words) = passphrases else {
+			return;
+		};
+
+		let mut bytes = Vec::new();
+		let mut offsets = vec![0u32];
+
+		for word in &words[step as usize..step as usize + count as usize] {
+			bytes.extend(word.bytes().map(|b| b as u32));
+			offsets.push(bytes.len() as u32);
+		}
+
+		if !bytes.is_empty() {
+			queue.write_buffer(&self.words_buffer, 0, bytemuck::cast_slice(&bytes));
+		}
+
+		queue.write_buffer(&self.word_offsets_buffer, 0, bytemuck::cast_slice(&offsets));
+	}
+}
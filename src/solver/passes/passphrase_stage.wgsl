@@ -0,0 +1,114 @@
+const WORKGROUP_SIZE = 256;
+
+const MAX_RESULTS_FOUND = 1398101;
+const MAX_MASK_LENGTH = 32;
+
+// candidate sources
+const MODE_WORDLIST = 0u;
+const MODE_MASK = 1u;
+
+struct Immediates {
+    step_low: u32,
+    step_high: u32,
+    offset: u32,
+    count: u32,
+    mode: u32
+};
+
+var<immediate> constants: Immediates;
+
+struct Block {
+    bytes: array<u32, SHA512_MAX_INPUT_SIZE>,
+    length: u32,
+};
+
+// normalized mnemonic sentence, shared by all candidates
+@group(0) @binding(0)
+var<storage, read> password: Block;
+
+// each position of the mask indexes into a charset
+struct Mask {
+    length: u32,
+    offsets: array<u32, MAX_MASK_LENGTH>,
+    sizes: array<u32, MAX_MASK_LENGTH>,
+};
+
+@group(0) @binding(1)
+var<storage, read> mask: Mask;
+
+@group(0) @binding(2)
+var<storage, read> charsets: array<u32>;
+
+// wordlist candidates of the current step, candidate[i] = words[offsets[i]..offsets[i + 1]]
+@group(0) @binding(3)
+var<storage, read> words: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> word_offsets: array<u32>;
+
+struct Output {
+    offset: u32,
+    hash: array<u32, SHA512_HASH_LENGTH>
+}
+
+@group(0) @binding(5)
+var<storage, read_write> outputs: array<Output, MAX_RESULTS_FOUND>;
+
+// appends the masked candidate to the salt, the last position of the mask is least significant
+fn mask_candidate(index: u64, salt: ptr<function, array<u32, SHA512_MAX_INPUT_SIZE>>, salt_len: u32) -> u32 {
+    var remainder = index;
+
+    for (var i = i32(mask.length) - 1; i >= 0; i--) {
+        let size = u64(mask.sizes[i]);
+
+        salt[salt_len + u32(i)] = charsets[mask.offsets[i] + u32(remainder % size)];
+        remainder = remainder / size;
+    }
+
+    return salt_len + mask.length;
+}
+
+// appends the wordlist candidate to the salt
+fn wordlist_candidate(thread: u32, salt: ptr<function, array<u32, SHA512_MAX_INPUT_SIZE>>, salt_len: u32) -> u32 {
+    var cursor = salt_len;
+
+    for (var i = word_offsets[thread]; i < word_offsets[thread + 1]; i++) {
+        salt[cursor] = words[i];
+        cursor += 1;
+    }
+
+    return cursor;
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(@builtin(global_invocation_id) global: vec3<u32>) {
+    let thread = global.x + constants.offset;
+    if thread >= constants.count {
+        return;
+    }
+
+    // b"mnemonic" + candidate passphrase
+    var salt = array<u32, SHA512_MAX_INPUT_SIZE>();
+    var prefix = array<u32, 8>(109, 110, 101, 109, 111, 110, 105, 99);
+
+    for (var i = 0; i < 8; i++) {
+        salt[i] = prefix[i];
+    }
+
+    var salt_len = 8u;
+    if constants.mode == MODE_MASK {
+        let step = (u64(constants.step_high) << 32u) | u64(constants.step_low);
+        salt_len = mask_candidate(step + u64(thread), &salt, salt_len);
+    } else {
+        salt_len = wordlist_candidate(thread, &salt, salt_len);
+    }
+
+    // derive mnemonic seed
+    var key = password.bytes;
+    var seed: array<u32, SHA512_MAX_INPUT_SIZE>;
+    pbkdf2(&key, password.length, &salt, salt_len, 2048, &seed);
+
+    // derive master extended key
+    var master = master_extended_key(&seed);
+    outputs[thread] = Output(thread, master);
+}
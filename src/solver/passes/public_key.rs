@@ -0,0 +1,465 @@
+use super::super::*;
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Immediates {
+	pub(crate) offset: u32,
+	pub(crate) count: u32,
+	/// index of the path, in the order of `paths::Paths::iter`
+	pub(crate) path: u32,
+	/// index of the group whose parent key is derived
+	pub(crate) group: u32,
+	/// child number of the path in its group
+	pub(crate) child: u32,
+	pub(crate) kinds: u32,
+	pub(crate) mask: u32,
+	pub(crate) hashes: u32,
+}
+
+/// Derives the child public keys at every derivation path for every master extended key. Paths are grouped by their parent,
+/// whose extended key is derived once per group and kept in storage, then each path of the group derives only its last level.
+/// Public keys are hashed into the fingerprints of each address kind among the targets, only hits are read back
+pub(crate) struct PublicKeyPass {
+	pub constants: Immediates,
+	pub parent_pipeline: wgpu::ComputePipeline,
+	pub pipeline: wgpu::ComputePipeline,
+	pub slots: Vec<PublicKeySlot>,
+	/// child numbers of the paths of each group
+	pub groups: Vec<Vec<u32>>,
+}
+
+/// Bloom filter of the fingerprints of the targets, probed on the GPU before hits are confirmed exactly on the CPU.
+/// Fingerprints are already uniform hashes, so each of their first words masked to the size of the filter is an independent bit index
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Bloom {
+	/// a power of two, at most 2^32 so bit indices fit a word
+	pub bits: u64,
+	pub hashes: u32,
+}
+
+impl Bloom {
+	/// Bits per fingerprint when the device has room, a false positive rate around 2e-6 with every hash
+	pub(crate) const BITS_PER_FINGERPRINT: u64 = 64;
+
+	/// Words of a fingerprint
+	pub(crate) const MAX_HASHES: u32 = 5;
+
+	/// Filter of `fingerprints` fitting in `limit` bytes
+	pub(crate) fn new(fingerprints: usize, limit: u64) -> Bloom {
+		let wanted = (fingerprints as u64).saturating_mul(Self::BITS_PER_FINGERPRINT).next_power_of_two();
+		let available = 1 << (limit.saturating_mul(8).max(32).ilog2());
+		let bits = wanted.min(available).clamp(32, 1 << 32);
+
+		// optimal number of hashes, bounded by the words of a fingerprint
+		let hashes = match fingerprints {
+			0 => 1,
+			_ => ((bits as f64 / fingerprints as f64) * std::f64::consts::LN_2).round().clamp(1.0, Self::MAX_HASHES as f64) as u32,
+		};
+
+		Bloom { bits, hashes }
+	}
+
+	pub(crate) fn mask(&self) -> u32 {
+		(self.bits - 1) as u32
+	}
+
+	pub(crate) fn size(&self) -> wgpu::BufferAddress {
+		self.bits / 8
+	}
+
+	fn indices(&self, fingerprint: &[u8; 20]) -> impl Iterator<Item = usize> {
+		let (fingerprint, mask) = (*fingerprint, self.mask());
+		(0..self.hashes as usize).map(move |i| (u32::from_be_bytes(fingerprint[i * 4..(i + 1) * 4].try_into().unwrap()) & mask) as usize)
+	}
+
+	pub(crate) fn insert(&self, words: &mut [u32], fingerprint: &[u8; 20]) {
+		for index in self.indices(fingerprint) {
+			words[index / 32] |= 1 << (index % 32);
+		}
+	}
+
+	/// Whether the fingerprint may be among the targets, as probed by the shader
+	#[cfg(test)]
+	pub(crate) fn contains(&self, words: &[u32], fingerprint: &[u8; 20]) -> bool {
+		self.indices(fingerprint).all(|index| words[index / 32] & (1 << (index % 32)) != 0)
+	}
+
+	/// Expected fraction of fingerprints that aren't targets but pass the filter
+	pub(crate) fn false_positive_rate(&self, fingerprints: usize) -> f64 {
+		let hashes = self.hashes as f64;
+		(1.0 - (-hashes * fingerprints as f64 / self.bits as f64).exp()).powf(hashes)
+	}
+}
+
+// a single master extended key at a single group of paths always fits in the hits buffer
+const _: () = assert!(paths::MAX_PATHS <= PublicKeyPass::MAX_HITS);
+
+/// Hits of the master extended keys in a single buffer of the derivation or passphrase pass
+pub(crate) struct PublicKeySlot {
+	pub bind_group: wgpu::BindGroup,
+	pub hits_count_buffer: wgpu::Buffer,
+	pub hits_count_buffer_dest: wgpu::Buffer,
+	pub hits_buffer: wgpu::Buffer,
+	pub hits_buffer_dest: wgpu::Buffer,
+}
+
+impl PublicKeyPass {
+	/// WGSL has no imports, shared modules are concatenated
+	pub(crate) const SOURCE: &str = concat!(
+		include_str!("../../shaders/sha512.wgsl"),
+		include_str!("../../shaders/pbkdf2.wgsl"),
+		include_str!("../../shaders/secp256k1.wgsl"),
+		include_str!("../../shaders/hash160.wgsl"),
+		include_str!("../../shaders/keccak.wgsl"),
+		include_str!("public_key_stage.wgsl")
+	);
+
+	pub(crate) const WORKGROUP_SIZE: u32 = 256;
+
+	/// Hits read back at once, true matches and false positives of the filter. Steps with more hits are split until they fit,
+	/// as a path has at most one hit per master extended key this is also the upper bound on `paths::MAX_PATHS`
+	pub(crate) const MAX_HITS: usize = 65536;
+
+	/// Above this false positive rate, most hits are confirmed on the CPU to be false and steps are split to read them back
+	const FALSE_POSITIVE_WARNING: f64 = 1e-4;
+
+	/// Windows of 8 bits across a 256 bit scalar, each with 255 non zero digits
+	pub(crate) const GENERATOR_WINDOWS: usize = 32;
+	pub(crate) const GENERATOR_DIGITS: usize = 255;
+
+	/// `master_keys` hold the `types::DerivationsOutput`s of the derivation or passphrase pass, one slot is created per buffer
+	pub(crate) fn new(device: &wgpu::Device, master_keys: &[&wgpu::Buffer], paths: &paths::Paths, targets: &targets::Targets) -> Result<PublicKeyPass, Error> {
+		utils::check_immediates::<Immediates>(device, "public key")?;
+
+		// prepare buffers
+		let parents = paths.groups().map(|(parent, _)| types::Path::new(parent)).collect::<Vec<_>>();
+		let parents_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("public_key_parents"),
+			contents: bytemuck::cast_slice(&parents),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		let groups = paths.groups().map(|(_, children)| children.iter().map(|&child| u32::from(child)).collect::<Vec<_>>()).collect::<Vec<_>>();
+
+		let generator_table_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("public_key_generator_table"),
+			contents: bytemuck::cast_slice(&Self::generator_table()),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		// the filter is sized to the device, a fraction of the size of the fingerprints of millions of targets
+		let fingerprints = targets.fingerprints().count();
+		let limit = device.limits().max_storage_buffer_binding_size.min(device.limits().max_buffer_size);
+		let bloom = Bloom::new(fingerprints, limit);
+
+		let false_positive_rate = bloom.false_positive_rate(fingerprints);
+		log::info!(target: "solver::public_key_stage", "Filter of {} Fingerprints: {} bytes, {} Hashes, False Positive Rate = {:.2e}", fingerprints, bloom.size(), bloom.hashes, false_positive_rate);
+
+		if false_positive_rate > Self::FALSE_POSITIVE_WARNING {
+			log::warn!(
+				target: "solver::public_key_stage",
+				"{} targets exceed the device's storage buffer limit of {} bytes for an accurate filter, {:.2e} of public keys are false positives confirmed on the CPU",
+				targets.len(),
+				limit,
+				false_positive_rate
+			);
+		}
+
+		// mapped memory is write only, bits are set at random so the filter is built on the CPU first
+		let mut filter = vec![0u32; (bloom.size() / 4) as usize];
+		for fingerprint in targets.fingerprints() {
+			bloom.insert(&mut filter, fingerprint);
+		}
+
+		let filter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("public_key_filter"),
+			contents: bytemuck::cast_slice(&filter),
+			usage: wgpu::BufferUsages::STORAGE,
+		});
+
+		// compile shader
+		let descriptor = wgpu::ShaderModuleDescriptor {
+			label: Some("public_key_main"),
+			source: wgpu::ShaderSource::Wgsl(Self::SOURCE.into()),
+		};
+
+		let shader = device.create_shader_module(descriptor);
+
+		// configure bind group layout
+		let entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::COMPUTE,
+			ty: wgpu::BindingType::Buffer {
+				ty: wgpu::BufferBindingType::Storage { read_only },
+				has_dynamic_offset: false,
+				min_binding_size: None,
+			},
+			count: None,
+		};
+
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("public_key_bind_group_layout"),
+			entries: &[entry(0, true), entry(1, true), entry(2, true), entry(3, true), entry(4, false), entry(5, false), entry(6, false)],
+		});
+
+		// configure pipeline layout
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("public_key_pipeline_layout"),
+			bind_group_layouts: &[Some(&bind_group_layout)],
+			immediate_size: std::mem::size_of::<Immediates>() as u32,
+		});
+
+		// create compute pipelines, parent keys are derived by one entry point and their children by the other
+		let pipeline = |label, entry_point| {
+			device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+				label: Some(label),
+				module: &shader,
+				entry_point: Some(entry_point),
+				layout: Some(&pipeline_layout),
+				// defaults
+				cache: None,
+				compilation_options: Default::default(),
+			})
+		};
+
+		let parent_pipeline = pipeline("public_key_parent_pipeline", "parent");
+		let pipeline = pipeline("public_key_pipeline", "main");
+
+		// hits of every buffer of master extended keys
+		let slots = master_keys
+			.iter()
+			.map(|master_keys| {
+				// extended key at the parent of the current group, for each master extended key
+				let parent_keys_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("public_key_parent_keys"),
+					size: std::mem::size_of::<[types::ExtendedKey; MAX_RESULTS_FOUND]>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::STORAGE,
+					mapped_at_creation: false,
+				});
+
+				let hits_count_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("public_key_hits_count"),
+					size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+					mapped_at_creation: false,
+				});
+
+				let hits_count_buffer_dest = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("public_key_hits_count_dest"),
+					size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+					mapped_at_creation: false,
+				});
+
+				let hits_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("public_key_hits"),
+					size: std::mem::size_of::<[types::Hit; Self::MAX_HITS]>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+					mapped_at_creation: false,
+				});
+
+				let hits_buffer_dest = device.create_buffer(&wgpu::BufferDescriptor {
+					label: Some("public_key_hits_dest"),
+					size: std::mem::size_of::<[types::Hit; Self::MAX_HITS]>() as wgpu::BufferAddress,
+					usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+					mapped_at_creation: false,
+				});
+
+				// configure bind group
+				let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("public_key_bind_group"),
+					layout: &bind_group_layout,
+					entries: &[
+						wgpu::BindGroupEntry {
+							binding: 0,
+							resource: master_keys.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 1,
+							resource: parents_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 2,
+							resource: generator_table_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 3,
+							resource: filter_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 4,
+							resource: hits_count_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 5,
+							resource: hits_buffer.as_entire_binding(),
+						},
+						wgpu::BindGroupEntry {
+							binding: 6,
+							resource: parent_keys_buffer.as_entire_binding(),
+						},
+					],
+				});
+
+				PublicKeySlot {
+					bind_group,
+					hits_count_buffer,
+					hits_count_buffer_dest,
+					hits_buffer,
+					hits_buffer_dest,
+				}
+			})
+			.collect();
+
+		Ok(PublicKeyPass {
+			parent_pipeline,
+			pipeline,
+			slots,
+			groups,
+			constants: Immediates {
+				kinds: targets.kinds().iter().fold(0, |kinds, kind| kinds | kind.flag()),
+				mask: bloom.mask(),
+				hashes: bloom.hashes,
+				..bytemuck::Zeroable::zeroed()
+			},
+		})
+	}
+
+	/// Derives the public keys of the first `count` master extended keys of a slot at every path, returning the hits among the targets ordered by offset and path
+	pub(crate) fn find(&self, device: &wgpu::Device, queue: &wgpu::Queue, slot: usize, count: u32, dispatch: u32) -> Result<Box<[types::Hit]>, Error> {
+		let max_threads = dispatch * Self::WORKGROUP_SIZE;
+		let mut hits = self.find_range(device, queue, &self.slots[slot], 0..count, 0..self.groups.len(), max_threads)?;
+		hits.sort_unstable();
+
+		log::info!(target: "solver::public_key_stage", "Hits Found: {}", hits.len());
+		Ok(hits.into_boxed_slice())
+	}
+
+	/// Hits of a range of master extended keys at the paths of a range of groups. When the hits buffer overflows, the range is split in halves
+	/// and each is derived again, down to a single master extended key and group, whose hits always fit as there is at most one per path
+	fn find_range(&self, device: &wgpu::Device, queue: &wgpu::Queue, slot: &PublicKeySlot, keys: std::ops::Range<u32>, groups: std::ops::Range<usize>, max_threads: u32) -> Result<Vec<types::Hit>, Error> {
+		queue.write_buffer(&slot.hits_count_buffer, 0, bytemuck::cast_slice(&[0u32]));
+		let mut previous = None;
+		let mut path = self.groups[..groups.start].iter().map(|children| children.len() as u32).sum();
+
+		for group in groups.clone() {
+			let children = &self.groups[group];
+			log::debug!(target: "solver::public_key_stage", "Group = {}, Paths = {}, MasterKeys = {:?}, WorkgroupSize = {}", group, children.len(), keys, Self::WORKGROUP_SIZE);
+
+			// the parent key of the group is derived once, then only the last level of each of its paths
+			let (offset, count) = (keys.start, keys.end);
+			let parent = Immediates {
+				group: group as u32,
+				offset,
+				count,
+				..self.constants
+			};
+			self.dispatch(device, queue, slot, &self.parent_pipeline, parent, max_threads, &mut previous)?;
+
+			for &child in children {
+				let constants = Immediates {
+					path,
+					child,
+					offset,
+					count,
+					..self.constants
+				};
+				self.dispatch(device, queue, slot, &self.pipeline, constants, max_threads, &mut previous)?;
+				path += 1;
+			}
+		}
+
+		// copy hits to CPU mapped buffers once the last chunk completes, then read them back
+		let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("public_key_copy_encoder") });
+		encoder.copy_buffer_to_buffer(&slot.hits_count_buffer, 0, &slot.hits_count_buffer_dest, 0, slot.hits_count_buffer.size());
+		encoder.copy_buffer_to_buffer(&slot.hits_buffer, 0, &slot.hits_buffer_dest, 0, slot.hits_buffer.size());
+		let submission = queue.submit([encoder.finish()]);
+
+		let hits_count = utils::inspect_buffer::<u32, _, _>(device, &slot.hits_count_buffer_dest, submission.clone(), |count| count[0] as usize)?;
+
+		// hits buffer was full, its hits are incomplete so both halves are derived again
+		if hits_count > Self::MAX_HITS {
+			log::debug!(target: "solver::public_key_stage", "More than {} hits found: {}, splitting MasterKeys = {:?}, Groups = {:?}", Self::MAX_HITS, hits_count, keys, groups);
+
+			let (first, second) = match keys.len() {
+				1 => {
+					let middle = groups.start + groups.len() / 2;
+					((keys.clone(), groups.start..middle), (keys, middle..groups.end))
+				}
+				_ => {
+					let middle = keys.start + (keys.end - keys.start) / 2;
+					((keys.start..middle, groups.clone()), (middle..keys.end, groups))
+				}
+			};
+
+			let mut hits = self.find_range(device, queue, slot, first.0, first.1, max_threads)?;
+			hits.extend(self.find_range(device, queue, slot, second.0, second.1, max_threads)?);
+			return Ok(hits);
+		}
+
+		utils::inspect_buffer::<types::Hit, _, _>(device, &slot.hits_buffer_dest, submission, move |hits| hits[..hits_count].to_vec())
+	}
+
+	/// Runs a pipeline over the master extended keys in smaller dispatches to avoid GPU timeouts. Each chunk waits on the one before,
+	/// so the next one is always queued behind the running one
+	#[allow(clippy::too_many_arguments)]
+	fn dispatch(
+		&self,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		slot: &PublicKeySlot,
+		pipeline: &wgpu::ComputePipeline,
+		mut constants: Immediates,
+		max_threads: u32,
+		previous: &mut Option<wgpu::SubmissionIndex>,
+	) -> Result<(), Error> {
+		while constants.offset < constants.count {
+			let threads = (constants.count - constants.offset).min(max_threads);
+			let dispatch = threads.div_ceil(Self::WORKGROUP_SIZE);
+
+			let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("public_key_pass_encoder") });
+
+			{
+				let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+					label: Some("public_key_pass"),
+					timestamp_writes: None,
+				});
+
+				pass.set_pipeline(pipeline);
+				pass.set_bind_group(0, &slot.bind_group, &[]);
+
+				pass.set_immediates(0, bytemuck::cast_slice(&[constants]));
+				pass.dispatch_workgroups(dispatch, 1, 1);
+			}
+
+			let submission = queue.submit([encoder.finish()]);
+			if let Some(previous) = previous.replace(submission) {
+				utils::wait(device, previous)?;
+			}
+
+			constants.offset = constants.offset.saturating_add(threads);
+		}
+
+		Ok(())
+	}
+
+	/// Multiples of the generator for every 8 bit window of a scalar: `table[window * 255 + digit - 1] = digit * 2^(8 * window) * G`
+	pub(crate) fn generator_table() -> Vec<types::AffinePoint> {
+		let secp256k1 = bitcoin::key::Secp256k1::signing_only();
+		let mut table = Vec::with_capacity(Self::GENERATOR_WINDOWS * Self::GENERATOR_DIGITS);
+
+		for window in 0..Self::GENERATOR_WINDOWS {
+			for digit in 1..=Self::GENERATOR_DIGITS {
+				// every multiple is below the curve order, so a valid secret key
+				let mut scalar = [0; 32];
+				scalar[31 - window] = digit as u8;
+
+				let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&scalar).expect("Generator multiples should be valid secret keys");
+				table.push(types::AffinePoint::new(&secret_key.public_key(&secp256k1)));
+			}
+		}
+
+		table
+	}
+}
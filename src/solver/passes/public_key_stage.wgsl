@@ -0,0 +1,272 @@
+// dispatch size is dynamic: X=*,Y=1,Z=1
+const WORKGROUP_SIZE = 256;
+
+const MAX_RESULTS_FOUND = 1398101;
+const MAX_PATH_DEPTH = 8;
+const MAX_HITS = 65536;
+
+// address kinds among the targets, only these are derived for each candidate
+const KIND_P2PKH = 1u;
+const KIND_P2SH_P2WPKH = 2u;
+const KIND_P2WPKH = 4u;
+const KIND_P2TR = 8u;
+const KIND_EVM = 16u;
+
+// sha256(b"TapTweak"), as big endian words
+const TAP_TWEAK_TAG = array<u32, 8>(0xe80fe163u, 0x9c9ca050u, 0xe3af1b39u, 0xc143c63eu, 0x429cbcebu, 0x15d940fbu, 0xb5c5a1f4u, 0xaf57c5e9u);
+
+struct Immediates {
+    offset: u32,
+    count: u32,
+    path: u32,
+    group: u32,
+    child: u32,
+    kinds: u32,
+    mask: u32,
+    hashes: u32
+};
+
+var<immediate> constants: Immediates;
+
+// master extended keys, as output by the derivation or passphrase stage
+struct MasterKey {
+    offset: u32,
+    hash: array<u32, SHA512_HASH_LENGTH>
+}
+
+@group(0) @binding(0)
+var<storage, read> master_keys: array<MasterKey, MAX_RESULTS_FOUND>;
+
+// child indices of a derivation path, indices from 2^31 are hardened
+struct Path {
+    depth: u32,
+    indices: array<u32, MAX_PATH_DEPTH>,
+};
+
+// parent path of every group of derivation paths
+@group(0) @binding(1)
+var<storage, read> parents: array<Path>;
+
+@group(0) @binding(2)
+var<storage, read> generator_table: array<AffinePoint>;
+
+// bloom filter of the 20 byte fingerprints of the targets, indexed by their big endian words
+@group(0) @binding(3)
+var<storage, read> fingerprint_filter: array<u32>;
+
+// candidate whose public key at a path matched the fingerprint of a target
+struct Hit {
+    offset: u32,
+    path: u32,
+};
+
+@group(0) @binding(4)
+var<storage, read_write> hits_count: atomic<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> hits: array<Hit, MAX_HITS>;
+
+// private key and chain code of an extended key, as little endian limbs
+struct ExtendedKey {
+    private_key: array<u32, 8>,
+    chain_code: array<u32, 8>,
+};
+
+// extended key at the parent of the current group, for each master extended key
+@group(0) @binding(6)
+var<storage, read_write> parent_keys: array<ExtendedKey, MAX_RESULTS_FOUND>;
+
+// private key or chain code of an extended key, from 32 sparse big endian bytes into little endian limbs
+fn extended_key_limbs(extended_key: ptr<function, array<u32, SHA512_HASH_LENGTH>>, offset: u32) -> array<u32, 8> {
+    var limbs: array<u32, 8>;
+
+    for (var i = 0u; i < 8u; i++) {
+        let start = offset + (7u - i) * 4u;
+        limbs[i] = (extended_key[start] << 24u) | (extended_key[start + 1u] << 16u) | (extended_key[start + 2u] << 8u) | extended_key[start + 3u];
+    }
+
+    return limbs;
+}
+
+// writes little endian limbs as 32 sparse big endian bytes
+fn store_limbs(limbs: array<u32, 8>, dest: ptr<function, array<u32, SHA512_MAX_INPUT_SIZE>>, offset: u32) {
+    for (var i = 0u; i < 8u; i++) {
+        let start = offset + (7u - i) * 4u;
+
+        dest[start] = limbs[i] >> 24u;
+        dest[start + 1u] = (limbs[i] >> 16u) & 0xFFu;
+        dest[start + 2u] = (limbs[i] >> 8u) & 0xFFu;
+        dest[start + 3u] = limbs[i] & 0xFFu;
+    }
+}
+
+// derives a child of an extended private key, indices from 2^31 are hardened.
+// an invalid child key has a chance below 2^-127 per derivation, and isn't checked for
+fn child_extended_key(parent: ExtendedKey, index: u32) -> ExtendedKey {
+    var data = array<u32, SHA512_MAX_INPUT_SIZE>();
+
+    if index >= 0x80000000u {
+        // 0x00 || private key || index
+        store_limbs(parent.private_key, &data, 1u);
+    } else {
+        // compressed public key || index
+        let public_key = generator_mul(parent.private_key);
+
+        data[0] = 2u + (public_key.y[0] & 1u);
+        store_limbs(public_key.x, &data, 1u);
+    }
+
+    data[33] = index >> 24u;
+    data[34] = (index >> 16u) & 0xFFu;
+    data[35] = (index >> 8u) & 0xFFu;
+    data[36] = index & 0xFFu;
+
+    // chain code padded with 96 zeroes
+    var chain_code = array<u32, SHA512_MAX_INPUT_SIZE>();
+    store_limbs(parent.chain_code, &chain_code, 0u);
+
+    var child: array<u32, SHA512_HASH_LENGTH>;
+    hmac_sha512(&data, 37u, &chain_code, &child);
+
+    // child private key = IL + parent private key mod n, the chain code is IR
+    return ExtendedKey(add_mod(extended_key_limbs(&child, 0u), parent.private_key, CURVE_N), extended_key_limbs(&child, 32u));
+}
+
+// whether every bit of the fingerprint is set in the filter, false positives are confirmed on the CPU
+fn contains_fingerprint(fingerprint: array<u32, 5>) -> bool {
+    for (var i = 0u; i < constants.hashes; i++) {
+        let index = fingerprint[i] & constants.mask;
+
+        if (fingerprint_filter[index / 32u] & (1u << (index % 32u))) == 0u {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+// hash160 of the compressed public key, paid to by P2PKH and P2WPKH
+fn public_key_hash(point: AffinePoint) -> array<u32, 5> {
+    var data = array<u32, HASH_MAX_INPUT_SIZE>();
+
+    data[0] = 2u + (point.y[0] & 1u);
+    store_limbs(point.x, &data, 1u);
+
+    return hash160(&data, 33u);
+}
+
+// hash160 of the P2WPKH redeem script `0x00 0x14 <public key hash>`, paid to by nested segwit
+fn nested_script_hash(hash: array<u32, 5>) -> array<u32, 5> {
+    var data = array<u32, HASH_MAX_INPUT_SIZE>();
+    data[1] = 0x14u;
+
+    for (var i = 0u; i < 20u; i++) {
+        data[2u + i] = (hash[i / 4u] >> ((3u - i % 4u) * 8u)) & 0xFFu;
+    }
+
+    return hash160(&data, 22u);
+}
+
+// first 20 bytes of the BIP86 output key: P + tagged_hash("TapTweak", P.x) * G, with P's y made even
+fn taproot_output_key(point: AffinePoint) -> array<u32, 5> {
+    var internal_key = point;
+    if (point.y[0] & 1u) != 0u {
+        internal_key.y = u256_sub(FIELD_P, point.y);
+    }
+
+    // tag hash twice, then the x coordinate
+    var data = array<u32, HASH_MAX_INPUT_SIZE>();
+    for (var i = 0u; i < 32u; i++) {
+        let byte = (TAP_TWEAK_TAG[i / 4u] >> ((3u - i % 4u) * 8u)) & 0xFFu;
+        data[i] = byte;
+        data[32u + i] = byte;
+    }
+
+    store_limbs(internal_key.x, &data, 64u);
+    let tweak_words = sha256_short(&data, 96u);
+
+    var tweak: array<u32, 8>;
+    for (var i = 0u; i < 8u; i++) {
+        tweak[i] = tweak_words[7u - i];
+    }
+
+    // a tweak above the curve order has a chance below 2^-127, and isn't checked for
+    let one = array<u32, 8>(1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u);
+    let output_key = to_affine(point_add_mixed(JacobianPoint(internal_key.x, internal_key.y, one, false), generator_mul(tweak)));
+
+    return array<u32, 5>(output_key.x[7], output_key.x[6], output_key.x[5], output_key.x[4], output_key.x[3]);
+}
+
+// last 20 bytes of keccak256 over the uncompressed public key without its prefix
+fn evm_address(point: AffinePoint) -> array<u32, 5> {
+    var words: array<u32, 16>;
+    for (var i = 0u; i < 8u; i++) {
+        words[i] = point.x[7u - i];
+        words[8u + i] = point.y[7u - i];
+    }
+
+    let hash = keccak256_64(words);
+    return array<u32, 5>(hash[3], hash[4], hash[5], hash[6], hash[7]);
+}
+
+fn matches_target(point: AffinePoint) -> bool {
+    let kinds = constants.kinds;
+
+    if (kinds & (KIND_P2PKH | KIND_P2WPKH | KIND_P2SH_P2WPKH)) != 0u {
+        let hash = public_key_hash(point);
+
+        if (kinds & (KIND_P2PKH | KIND_P2WPKH)) != 0u && contains_fingerprint(hash) {
+            return true;
+        }
+
+        if (kinds & KIND_P2SH_P2WPKH) != 0u && contains_fingerprint(nested_script_hash(hash)) {
+            return true;
+        }
+    }
+
+    if (kinds & KIND_P2TR) != 0u && contains_fingerprint(taproot_output_key(point)) {
+        return true;
+    }
+
+    return (kinds & KIND_EVM) != 0u && contains_fingerprint(evm_address(point));
+}
+
+// derives the parent key of a group once for every master extended key, its paths then only derive their last level
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn parent(@builtin(global_invocation_id) global: vec3<u32>) {
+    let idx = global.x + constants.offset;
+    if idx >= constants.count {
+        return;
+    }
+
+    var hash = master_keys[idx].hash;
+    var extended_key = ExtendedKey(extended_key_limbs(&hash, 0u), extended_key_limbs(&hash, 32u));
+    let path = parents[constants.group];
+
+    for (var level = 0u; level < path.depth; level++) {
+        extended_key = child_extended_key(extended_key, path.indices[level]);
+    }
+
+    parent_keys[idx] = extended_key;
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(@builtin(global_invocation_id) global: vec3<u32>) {
+    let idx = global.x + constants.offset;
+    if idx >= constants.count {
+        return;
+    }
+
+    // last level of the path, from the parent key of its group
+    let extended_key = child_extended_key(parent_keys[idx], constants.child);
+    let point = generator_mul(extended_key.private_key);
+
+    // hits are compacted, the CPU confirms each against the exact targets
+    if matches_target(point) {
+        let slot = atomicAdd(&hits_count, 1u);
+
+        if slot < MAX_HITS {
+            hits[slot] = Hit(master_keys[idx].offset, constants.path);
+        }
+    }
+}
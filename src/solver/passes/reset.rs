@@ -2,14 +2,15 @@ use super::*;
 
 pub(crate) struct ResetPass {
 	pub pipeline: wgpu::ComputePipeline,
-	pub bind_group: wgpu::BindGroup,
+	/// one per slot of the filter pass
+	pub bind_groups: Vec<wgpu::BindGroup>,
 }
 
 impl ResetPass {
 	pub(crate) const DISPATCH_SIZE_X: u32 = 1;
 	pub(crate) const DISPATCH_SIZE_Y: u32 = 1;
 
-	/// Pass that resets the count buffer to zero.
+	/// Pass that resets the count buffer of a filter slot to zero.
 	pub(crate) fn new(device: &wgpu::Device, filter_pass: &filter::FilterPass) -> ResetPass {
 		// compile shader
 		let source = include_str!("reset_stage.wgsl");
@@ -41,14 +42,20 @@ impl ResetPass {
 		let bind_group_layout = device.create_bind_group_layout(&descriptor);
 
 		// configure bind groups
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("reset_bind_group"),
-			layout: &bind_group_layout,
-			entries: &[wgpu::BindGroupEntry {
-				binding: 1,
-				resource: filter_pass.count_buffer.as_entire_binding(),
-			}],
-		});
+		let bind_groups = filter_pass
+			.slots
+			.iter()
+			.map(|slot| {
+				device.create_bind_group(&wgpu::BindGroupDescriptor {
+					label: Some("reset_bind_group"),
+					layout: &bind_group_layout,
+					entries: &[wgpu::BindGroupEntry {
+						binding: 1,
+						resource: slot.count_buffer.as_entire_binding(),
+					}],
+				})
+			})
+			.collect();
 
 		// configure pipeline layout
 		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -68,6 +75,6 @@ impl ResetPass {
 			compilation_options: Default::default(),
 		});
 
-		ResetPass { pipeline, bind_group }
+		ResetPass { pipeline, bind_groups }
 	}
 }
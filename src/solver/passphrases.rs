@@ -0,0 +1,114 @@
+use std::io::BufRead;
+
+/// Maximum number of characters in a mask, bound by the size of the PBKDF2 salt
+pub(crate) const MAX_MASK_LENGTH: usize = 32;
+
+/// Candidate passphrases tried against a complete mnemonic, during passphrase recovery
+pub(crate) enum Passphrases {
+	/// NFKD normalized passphrases, read from a file
+	Wordlist(Vec<String>),
+	/// hashcat-style mask, each position holds the bytes it can take
+	Mask(Vec<Vec<u8>>),
+}
+
+impl Passphrases {
+	/// Builds the candidate source selected by config, if any
+	pub(crate) fn from_config(config: &super::super::Config) -> Result<Option<Passphrases>, String> {
+		match (config.passphrases.as_deref(), config.mask.as_deref()) {
+			(Some(_), Some(_)) => Err("Only one of a passphrase file or mask can be used".to_string()),
+			(Some(path), None) => Passphrases::wordlist(path).map(Some),
+			(None, Some(mask)) => Passphrases::mask(mask).map(Some),
+			(None, None) => Ok(None),
+		}
+	}
+
+	pub(crate) fn wordlist(path: &str) -> Result<Passphrases, String> {
+		use unicode_normalization::UnicodeNormalization;
+
+		let file = std::fs::File::open(path).map_err(|err| format!("Unable to open passphrase file `{}`: {}", path, err))?;
+		let reader = std::io::BufReader::new(file);
+
+		let mut words = Vec::new();
+		for (idx, line) in reader.lines().enumerate() {
+			let line = line.map_err(|err| format!("Unable to read passphrase file `{}`: {}", path, err))?;
+			let word = line.trim_end_matches('\r').nfkd().collect::<String>();
+
+			// b"mnemonic" prefix is part of the salt
+			if word.len() > super::types::Block::MAX_LENGTH - 8 {
+				return Err(format!("Passphrase on line {} is longer than {} bytes", idx + 1, super::types::Block::MAX_LENGTH - 8));
+			}
+
+			words.push(word);
+		}
+
+		Ok(Passphrases::Wordlist(words))
+	}
+
+	/// Parses a hashcat-style mask: ?l ?u ?d ?h ?H ?s ?a and ?? for a literal `?`
+	pub(crate) fn mask(mask: &str) -> Result<Passphrases, String> {
+		use unicode_normalization::UnicodeNormalization;
+
+		const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+		const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+		const DIGITS: &[u8] = b"0123456789";
+		const SPECIAL: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+		let mut positions = Vec::new();
+		let mut chars = mask.nfkd().collect::<String>().into_bytes().into_iter();
+
+		while let Some(char) = chars.next() {
+			if char != b'?' {
+				positions.push(vec![char]);
+				continue;
+			}
+
+			let charset = match chars.next() {
+				Some(b'l') => LOWER.to_vec(),
+				Some(b'u') => UPPER.to_vec(),
+				Some(b'd') => DIGITS.to_vec(),
+				Some(b'h') => b"0123456789abcdef".to_vec(),
+				Some(b'H') => b"0123456789ABCDEF".to_vec(),
+				Some(b's') => SPECIAL.to_vec(),
+				Some(b'a') => [LOWER, UPPER, DIGITS, SPECIAL].concat(),
+				Some(b'?') => vec![b'?'],
+				Some(other) => return Err(format!("Unknown mask charset `?{}`", other as char)),
+				None => return Err("Mask ends with an incomplete charset `?`".to_string()),
+			};
+
+			positions.push(charset);
+		}
+
+		if positions.len() > MAX_MASK_LENGTH {
+			return Err(format!("Mask is {} characters long, maximum is {}", positions.len(), MAX_MASK_LENGTH));
+		}
+
+		Ok(Passphrases::Mask(positions))
+	}
+
+	/// Number of candidate passphrases, `None` if it exceeds `u64`
+	pub(crate) fn space(&self) -> Option<u64> {
+		match self {
+			Passphrases::Wordlist(words) => Some(words.len() as u64),
+			Passphrases::Mask(positions) => positions.iter().try_fold(1u64, |acc, charset| acc.checked_mul(charset.len() as u64)),
+		}
+	}
+
+	/// Resolves a candidate index into a passphrase. Mirrors `mask_candidate` in `passphrase_stage.wgsl`
+	pub(crate) fn candidate(&self, index: u64) -> String {
+		match self {
+			Passphrases::Wordlist(words) => words[index as usize].clone(),
+			Passphrases::Mask(positions) => {
+				let mut remainder = index;
+				let mut bytes = vec![0; positions.len()];
+
+				// last position is least significant
+				for (byte, charset) in bytes.iter_mut().zip(positions).rev() {
+					*byte = charset[(remainder % charset.len() as u64) as usize];
+					remainder /= charset.len() as u64;
+				}
+
+				String::from_utf8_lossy(&bytes).into_owned()
+			}
+		}
+	}
+}
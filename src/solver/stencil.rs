@@ -0,0 +1,238 @@
+/// Supported number of words in a mnemonic sentence, 128 to 256 bits of entropy
+pub(crate) const MNEMONIC_LENGTHS: [usize; 5] = [12, 15, 18, 21, 24];
+/// Maximum number of words in a mnemonic sentence
+pub(crate) const MAX_MNEMONIC_WORDS: usize = 24;
+/// Number of words in a BIP39 word list, each word encodes 11 bits
+pub(crate) const WORD_LIST_SIZE: u32 = 2048;
+
+/// Describes the known words of a mnemonic sentence, and the candidate sets of the unknown words.
+/// Layout is shared with `mnemonic.wgsl`, where it is followed by the candidate word indices
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
+pub(crate) struct StencilHeader {
+	/// word indices of the mnemonic, unknown words are zeroed
+	pub(crate) indices: [u32; MAX_MNEMONIC_WORDS],
+	/// positions of the unknown words, the last unknown word is least significant
+	pub(crate) unknown: [u32; MAX_MNEMONIC_WORDS],
+	/// number of candidate words of each unknown word, a size of `WORD_LIST_SIZE` covers the whole word list
+	pub(crate) sizes: [u32; MAX_MNEMONIC_WORDS],
+	/// offsets of each unknown word's candidates, unused by unknown words covering the whole word list
+	pub(crate) offsets: [u32; MAX_MNEMONIC_WORDS],
+	/// number of words in the mnemonic
+	pub(crate) length: u32,
+	pub(crate) unknown_count: u32,
+	/// set when the last word is unknown over the whole word list, its size then only covers the entropy bits
+	/// and its checksum bits are computed from the entropy instead of enumerated
+	pub(crate) checksum: u32,
+}
+
+/// Known words of a mnemonic sentence, and the word indices each unknown word can take
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Stencil {
+	pub(crate) header: StencilHeader,
+	/// sorted word indices of unknown words restricted to part of the word list
+	pub(crate) candidates: Vec<u32>,
+}
+
+/// Number of checksum bits in the last word of a mnemonic, 4 for 12 words up to 8 for 24 words
+fn checksum_bits(length: u32) -> u32 {
+	length / 3
+}
+
+/// Packs 11 bit word indices into the entropy they encode, ignoring the checksum bits of the last word
+pub(crate) fn entropy(indices: &[u32]) -> Vec<u8> {
+	let mut bytes = [0u8; 33];
+	for (i, &index) in indices.iter().enumerate() {
+		for bit in 0..11 {
+			if index & (1 << (10 - bit)) != 0 {
+				let position = i * 11 + bit;
+				bytes[position / 8] |= 0x80 >> (position % 8);
+			}
+		}
+	}
+
+	bytes[..indices.len() * 4 / 3].to_vec()
+}
+
+/// Checksum of the entropy, the leading bits of its SHA256
+pub(crate) fn checksum(entropy: &[u8], bits: u32) -> u32 {
+	use bitcoin::hashes::{sha256, Hash};
+	(sha256::Hash::hash(entropy).as_byte_array()[0] >> (8 - bits)) as u32
+}
+
+/// Language of the stencil's word list, either selected by config or detected from its known words
+pub(crate) fn language(config: &super::super::Config) -> Result<bip39::Language, String> {
+	match config.language {
+		Some(language) => Ok(language),
+		None => detect_language(config.stencil.iter().map(|s| s.as_str())),
+	}
+}
+
+/// Finds the only word list in which every word of a stencil resolves. Stencils without known words default to English
+pub(crate) fn detect_language<'a, I: Iterator<Item = &'a str>>(words: I) -> Result<bip39::Language, String> {
+	let words = normalize(words);
+	let known = words.iter().filter(|w| *w != "_").collect::<Vec<_>>();
+
+	let candidates = bip39::Language::ALL.iter().filter(|l| known.iter().all(|w| resolve(w, **l).is_ok())).collect::<Vec<_>>();
+	match candidates.as_slice() {
+		_ if known.is_empty() => Ok(bip39::Language::English),
+		[language] => Ok(**language),
+		[] => Err("Known words don't belong to any single BIP39 word list".to_string()),
+		languages => Err(format!("Known words belong to multiple word lists {:?}, select one with --language", languages)),
+	}
+}
+
+/// Separator used when displaying a mnemonic sentence. Japanese uses the ideographic space, which NFKD normalizes to an ASCII space
+pub(crate) fn separator(language: bip39::Language) -> &'static str {
+	match language {
+		bip39::Language::Japanese => "\u{3000}",
+		_ => " ",
+	}
+}
+
+/// NFKD normalizes each word, words may also be passed as a single sentence separated by ASCII or ideographic spaces
+fn normalize<'a, I: Iterator<Item = &'a str>>(words: I) -> Vec<String> {
+	use unicode_normalization::UnicodeNormalization;
+	words.flat_map(str::split_whitespace).map(|w| w.nfkd().collect()).collect()
+}
+
+/// Resolves a word of the stencil into the sorted word indices it can take:
+/// `_` for any word, `ca*` and `?oo?` for words matching a pattern, `{cat,car,ca*}` for any of several words or patterns
+fn resolve(word: &str, language: bip39::Language) -> Result<Vec<u32>, String> {
+	use unicode_normalization::UnicodeNormalization;
+
+	if word == "_" {
+		return Ok((0..WORD_LIST_SIZE).collect());
+	}
+
+	let alternatives = match word.strip_prefix('{').and_then(|w| w.strip_suffix('}')) {
+		Some(inner) => inner.split(',').collect::<Vec<_>>(),
+		None => vec![word],
+	};
+
+	let mut indices = Vec::new();
+	for alternative in alternatives {
+		if !alternative.contains(['*', '?']) {
+			match language.find_word(alternative) {
+				Some(index) => indices.push(index as u32),
+				None => return Err(format!("Contains Unknown {} Word {}", language, alternative)),
+			}
+
+			continue;
+		}
+
+		// patterns match composed characters, so `?` covers an accented letter or a kana with its diacritic
+		let pattern = alternative.nfc().collect::<Vec<_>>();
+		let matching = language.word_list().iter().enumerate().filter(|(_, w)| matches(&pattern, &w.nfc().collect::<Vec<_>>()));
+		indices.extend(matching.map(|(index, _)| index as u32));
+	}
+
+	indices.sort_unstable();
+	indices.dedup();
+
+	match indices.is_empty() {
+		true => Err(format!("{} matches no {} Word", word, language)),
+		false => Ok(indices),
+	}
+}
+
+/// Glob matching, where `*` matches any number of characters and `?` exactly one
+fn matches(pattern: &[char], word: &[char]) -> bool {
+	match (pattern.first(), word.first()) {
+		(None, _) => word.is_empty(),
+		(Some('*'), _) => matches(&pattern[1..], word) || (!word.is_empty() && matches(pattern, &word[1..])),
+		(Some('?'), Some(_)) => matches(&pattern[1..], &word[1..]),
+		(Some(p), Some(w)) => p == w && matches(&pattern[1..], &word[1..]),
+		(Some(_), None) => false,
+	}
+}
+
+impl Stencil {
+	/// Parses a stencil of the given language, where `_`, patterns and sets of words mark an unknown word
+	pub(crate) fn parse<'a, I: Iterator<Item = &'a str>>(words: I, language: bip39::Language) -> Result<Stencil, String> {
+		let words = normalize(words);
+		if !MNEMONIC_LENGTHS.contains(&words.len()) {
+			return Err(format!("Expected 12, 15, 18, 21 or 24 words, got {}", words.len()));
+		}
+
+		let mut header: StencilHeader = bytemuck::Zeroable::zeroed();
+		let mut candidates = Vec::new();
+		header.length = words.len() as u32;
+
+		for (idx, word) in words.into_iter().enumerate() {
+			let indices = resolve(&word, language)?;
+
+			// a word resolving to a single index is known
+			if let [index] = indices[..] {
+				header.indices[idx] = index;
+				continue;
+			}
+
+			let unknown = header.unknown_count as usize;
+			header.unknown[unknown] = idx as u32;
+			header.sizes[unknown] = indices.len() as u32;
+			header.offsets[unknown] = candidates.len() as u32;
+			header.unknown_count += 1;
+
+			if indices.len() != WORD_LIST_SIZE as usize {
+				candidates.extend(indices);
+			} else if idx == header.length as usize - 1 {
+				header.sizes[unknown] = WORD_LIST_SIZE >> checksum_bits(header.length);
+				header.checksum = 1;
+			}
+		}
+
+		Ok(Stencil { header, candidates })
+	}
+
+	/// Number of candidates described by this stencil, `None` if it exceeds `u64`
+	pub(crate) fn space(&self) -> Option<u64> {
+		let sizes = &self.header.sizes[..self.header.unknown_count as usize];
+		sizes.iter().try_fold(1u64, |acc, &size| acc.checked_mul(size as u64))
+	}
+
+	/// Contents of the stencil buffer, the header followed by at least one candidate as runtime sized arrays can't be empty
+	pub(crate) fn to_bytes(&self) -> Vec<u8> {
+		let candidates = match self.candidates.is_empty() {
+			true => &[0][..],
+			false => &self.candidates[..],
+		};
+
+		[bytemuck::bytes_of(&self.header), bytemuck::cast_slice(candidates)].concat()
+	}
+
+	/// Resolves a candidate index into the word indices of a mnemonic. Mirrors `stencil_indices` in `mnemonic.wgsl`
+	pub(crate) fn indices(&self, index: u64) -> [u32; MAX_MNEMONIC_WORDS] {
+		let header = &self.header;
+		let mut indices = header.indices;
+		let mut remainder = index;
+
+		for unknown in (0..header.unknown_count as usize).rev() {
+			let size = header.sizes[unknown] as u64;
+			let digit = (remainder % size) as u32;
+			remainder /= size;
+
+			indices[header.unknown[unknown] as usize] = match header.sizes[unknown] {
+				WORD_LIST_SIZE => digit,
+				_ if header.checksum != 0 && unknown == header.unknown_count as usize - 1 => digit << checksum_bits(header.length),
+				_ => self.candidates[(header.offsets[unknown] + digit) as usize],
+			};
+		}
+
+		if header.checksum != 0 {
+			let length = header.length as usize;
+			indices[length - 1] |= checksum(&entropy(&indices[..length]), checksum_bits(header.length));
+		}
+
+		indices
+	}
+
+	/// Reconstructs the mnemonic of a candidate that passed the filter stage
+	pub(crate) fn mnemonic(&self, index: u64, language: bip39::Language) -> Result<bip39::Mnemonic, bip39::Error> {
+		let word_list = language.word_list();
+		let indices = self.indices(index);
+		let sentence = indices[..self.header.length as usize].iter().map(|&i| word_list[i as usize]).collect::<Vec<_>>().join(" ");
+
+		bip39::Mnemonic::parse_in_normalized(language, &sentence)
+	}
+}
@@ -1,20 +1,151 @@
+/// NFKD normalized UTF-8 bytes of a word list entry. Layout is shared with `derivation_stage.wgsl`
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct Bip39Word {
-	pub(crate) bytes: [u32; 8],
+	pub(crate) bytes: [u32; Bip39Word::MAX_LENGTH],
 	pub(crate) length: u32,
 }
 
+impl Bip39Word {
+	/// Longest normalized word across all languages is 33 bytes, in the Korean word list
+	pub(crate) const MAX_LENGTH: usize = 36;
+
+	pub(crate) fn new(word: &str) -> Bip39Word {
+		use unicode_normalization::UnicodeNormalization;
+
+		let normalized = word.nfkd().collect::<String>();
+		let mut bytes = [0; Bip39Word::MAX_LENGTH];
+		bytes.iter_mut().zip(normalized.as_bytes()).for_each(|(dest, src)| *dest = *src as u32);
+
+		Bip39Word { bytes, length: normalized.len() as u32 }
+	}
+}
+
+/// Bytes fitting into a single sha512 block, used as the PBKDF2 password or salt of a mnemonic seed
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Block {
+	pub(crate) bytes: [u32; Block::MAX_LENGTH],
+	pub(crate) length: u32,
+}
+
+impl Block {
+	pub(crate) const MAX_LENGTH: usize = 128;
+
+	/// b"mnemonic" followed by the NFKD normalized passphrase
+	pub(crate) fn salt(passphrase: &str) -> Result<Block, String> {
+		use unicode_normalization::UnicodeNormalization;
+
+		let salt = format!("mnemonic{}", passphrase.nfkd());
+		if salt.len() > Block::MAX_LENGTH {
+			return Err(format!("Passphrase is {} bytes long when normalized, maximum is {}", salt.len() - 8, Block::MAX_LENGTH - 8));
+		}
+
+		Ok(Block::from_bytes(salt.as_bytes()))
+	}
+
+	/// Normalized mnemonic sentence, hashed first if longer than a sha512 block as per HMAC
+	pub(crate) fn password(mnemonic: &bip39::Mnemonic) -> Block {
+		use bitcoin::hashes::{sha512, Hash};
+
+		let sentence = mnemonic.to_string();
+		match sentence.len() > Block::MAX_LENGTH {
+			true => Block::from_bytes(sha512::Hash::hash(sentence.as_bytes()).as_byte_array()),
+			false => Block::from_bytes(sentence.as_bytes()),
+		}
+	}
+
+	fn from_bytes(source: &[u8]) -> Block {
+		let mut bytes = [0; Block::MAX_LENGTH];
+		bytes.iter_mut().zip(source).for_each(|(dest, src)| *dest = *src as u32);
+
+		Block { bytes, length: source.len() as u32 }
+	}
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
 pub(crate) struct DerivationsOutput {
-	/// variant word for this output
-	pub(crate) word2: u32,
+	/// offset of the candidate relative to the start of its step
+	pub(crate) offset: u32,
 	/// sha512, represented as a 32bit array
 	pub(crate) hash: [u32; 64],
 }
 
-/// The 3rd word of a mnemonic sequence, that matches the given checksum when combined with entropy
-pub(crate) type Word = u32;
-/// Represents a verified P2PKH address as a 20-byte hash
-pub(crate) type PublicKeyHash = [u8; 20];
+/// Offset of a candidate relative to the start of its step, whose words match the mnemonic checksum
+pub(crate) type Offset = u32;
+
+impl DerivationsOutput {
+	/// Master extended private key, from the private key and chain code halves of the hash
+	pub(crate) fn extended_private_key(&self, network: bitcoin::Network) -> Result<bitcoin::bip32::Xpriv, bitcoin::secp256k1::Error> {
+		let combined = self.hash.map(|s| s as u8);
+
+		let mut chain_code = [0; 32];
+		chain_code.copy_from_slice(&combined[32..]);
+
+		Ok(bitcoin::bip32::Xpriv {
+			network: network.into(),
+			depth: 0,
+			parent_fingerprint: bitcoin::bip32::Fingerprint::from([0; 4]),
+			child_number: bitcoin::bip32::ChildNumber::Normal { index: 0 },
+			private_key: bitcoin::secp256k1::SecretKey::from_slice(&combined[..32])?,
+			chain_code: bitcoin::bip32::ChainCode::from(chain_code),
+		})
+	}
+}
+
+/// Child indices of a derivation path, as laid out in `public_key_stage.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Path {
+	pub(crate) depth: u32,
+	pub(crate) indices: [u32; Path::MAX_DEPTH],
+}
+
+impl Path {
+	pub(crate) const MAX_DEPTH: usize = 8;
+
+	pub(crate) fn new(path: &bitcoin::bip32::DerivationPath) -> Path {
+		let mut indices = [0; Path::MAX_DEPTH];
+		indices.iter_mut().zip(path.as_ref()).for_each(|(dest, &child)| *dest = u32::from(child));
+
+		Path { depth: path.len() as u32, indices }
+	}
+}
+
+/// Extended private key as 8 little endian limbs per half, the layout of `public_key_stage.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct ExtendedKey {
+	pub(crate) private_key: [u32; 8],
+	pub(crate) chain_code: [u32; 8],
+}
+
+/// Point of secp256k1 as 8 little endian limbs per coordinate, the layout of `secp256k1.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct AffinePoint {
+	pub(crate) x: [u32; 8],
+	pub(crate) y: [u32; 8],
+}
+
+impl AffinePoint {
+	pub(crate) fn new(public_key: &bitcoin::secp256k1::PublicKey) -> AffinePoint {
+		let uncompressed = public_key.serialize_uncompressed();
+		let limbs = |bytes: &[u8]| std::array::from_fn(|i| u32::from_be_bytes(bytes[(7 - i) * 4..(8 - i) * 4].try_into().unwrap()));
+
+		AffinePoint {
+			x: limbs(&uncompressed[1..33]),
+			y: limbs(&uncompressed[33..]),
+		}
+	}
+}
+
+/// Candidate whose public key at `paths[path]` matched the fingerprint of a target, as laid out in `public_key_stage.wgsl`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Hit {
+	/// offset of the candidate relative to the start of its step
+	pub(crate) offset: u32,
+	pub(crate) path: u32,
+}
@@ -1,20 +1,74 @@
 #![allow(unused)]
+use super::super::Error;
 use std::fmt::Debug;
 
-pub(crate) fn inspect_buffer<T: bytemuck::Pod, F: Fn(&[T]) + Send + Sync + 'static>(device: &wgpu::Device, buffer: &wgpu::Buffer, callback: F) {
+/// Maps a buffer for reading and passes its contents to `callback`, blocking until `submission`, the last to write the buffer, has completed.
+/// Later submissions keep running on the device
+pub(crate) fn inspect_buffer<T: bytemuck::Pod, R: Send + 'static, F: FnOnce(&[T]) -> R + Send + 'static>(
+	device: &wgpu::Device,
+	buffer: &wgpu::Buffer,
+	submission: wgpu::SubmissionIndex,
+	callback: F,
+) -> Result<R, Error> {
 	let buffer_ = buffer.clone();
+	let (send, recv) = flume::bounded(1);
+
 	buffer.map_async(wgpu::MapMode::Read, .., move |res| {
-		res.unwrap();
+		let output = res.map_err(|err| Error::Mapping(err.to_string())).map(|_| {
+			let range = buffer_.get_mapped_range(..);
+			let data: &[T] = bytemuck::cast_slice(range.as_ref());
 
-		let range = buffer_.get_mapped_range(..);
-		let data: &[T] = bytemuck::cast_slice(range.as_ref());
+			// Call the provided callback with the data
+			let output = callback(data);
 
-		// Call the provided callback with the data
-		callback(data);
+			drop(range);
+			buffer_.unmap();
+			output
+		});
 
-		drop(range);
-		buffer_.unmap();
+		let _ = send.send(output);
 	});
 
-	device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
+	device
+		.poll(wgpu::PollType::Wait {
+			submission_index: Some(submission),
+			timeout: None,
+		})
+		.map_err(|err| Error::Device(format!("Unable to poll device: {}", err)))?;
+
+	recv.recv().map_err(|_| Error::Mapping("Buffer was never mapped".to_string()))?
+}
+
+/// Blocks until `submission` has completed, later submissions keep running on the device
+pub(crate) fn wait(device: &wgpu::Device, submission: wgpu::SubmissionIndex) -> Result<(), Error> {
+	device
+		.poll(wgpu::PollType::Wait {
+			submission_index: Some(submission),
+			timeout: None,
+		})
+		.map(drop)
+		.map_err(|err| Error::Device(format!("Unable to poll device: {}", err)))
+}
+
+/// Fails unless the device accepts the immediates `T` of a pass's pipeline
+pub(crate) fn check_immediates<T>(device: &wgpu::Device, pass: &str) -> Result<(), Error> {
+	let (size, limit) = (std::mem::size_of::<T>() as u32, device.limits().max_immediate_size);
+
+	match size <= limit {
+		true => Ok(()),
+		false => Err(Error::Device(format!("Immediates of the {} pass take {} bytes, the device allows {}", pass, size, limit))),
+	}
+}
+
+/// Hands the results of a step to the monitoring thread. Blocks while its queue is full, so the device is only submitted more work once the CPU catches up.
+/// Fails once the monitoring thread has stopped
+pub(crate) fn send(sender: &flume::Sender<super::StageComputation>, output: super::StageComputation) -> Result<(), Error> {
+	if sender.is_full() {
+		log::debug!(target: "solver::back_pressure", "Monitoring thread is behind, waiting before step {}: Queue length = {}", output.step, sender.len());
+	}
+
+	let step = output.step;
+	sender
+		.send(output)
+		.map_err(|_| Error::Mapping(format!("Results of step {} were read back after the monitoring thread stopped", step)))
 }
@@ -0,0 +1,427 @@
+use super::Error;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::{secp256k1, Address, AddressType, CompressedPublicKey, Network};
+use std::io::{BufRead, Read, Seek, Write};
+
+/// Keccak-256 hash of an uncompressed public key, truncated to its last 20 bytes
+pub type EvmAddress = [u8; 20];
+
+/// Script types a candidate key is checked against
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AddressKind {
+	/// legacy `1…` addresses
+	P2pkh,
+	/// nested segwit `3…` addresses, other P2SH scripts are never matched
+	P2shP2wpkh,
+	/// native segwit `bc1q…` addresses
+	P2wpkh,
+	/// taproot `bc1p…` addresses, with the BIP86 key-path tweak
+	P2tr,
+	/// ethereum and other EVM chains' `0x…` addresses
+	Evm,
+}
+
+impl AddressKind {
+	pub fn of(target: &Target) -> Result<AddressKind, String> {
+		let address = match target {
+			Target::Bitcoin(address) => address,
+			Target::Evm(_) => return Ok(AddressKind::Evm),
+		};
+
+		match address.address_type() {
+			Some(AddressType::P2pkh) => Ok(AddressKind::P2pkh),
+			Some(AddressType::P2sh) => Ok(AddressKind::P2shP2wpkh),
+			Some(AddressType::P2wpkh) => Ok(AddressKind::P2wpkh),
+			Some(AddressType::P2tr) => Ok(AddressKind::P2tr),
+			other => Err(format!("Unsupported address type {:?}: {}", other, address)),
+		}
+	}
+
+	/// BIP44, BIP49, BIP84 and BIP86 purpose of wallets using this kind of address
+	pub fn purpose(self) -> u32 {
+		match self {
+			AddressKind::P2pkh | AddressKind::Evm => 44,
+			AddressKind::P2shP2wpkh => 49,
+			AddressKind::P2wpkh => 84,
+			AddressKind::P2tr => 86,
+		}
+	}
+
+	/// SLIP-44 coin type of wallets using this kind of address, every bitcoin test network shares coin type 1
+	pub fn coin_type(self, network: Network) -> u32 {
+		match (self, network) {
+			(AddressKind::Evm, _) => 60,
+			(_, Network::Bitcoin) => 0,
+			_ => 1,
+		}
+	}
+
+	/// Flag of this kind in the `kinds` of `public_key_stage.wgsl`
+	pub(crate) fn flag(self) -> u32 {
+		match self {
+			AddressKind::P2pkh => 1,
+			AddressKind::P2shP2wpkh => 2,
+			AddressKind::P2wpkh => 4,
+			AddressKind::P2tr => 8,
+			AddressKind::Evm => 16,
+		}
+	}
+
+	/// Name of this kind in match records
+	pub fn name(self) -> &'static str {
+		match self {
+			AddressKind::P2pkh => "p2pkh",
+			AddressKind::P2shP2wpkh => "p2sh-p2wpkh",
+			AddressKind::P2wpkh => "p2wpkh",
+			AddressKind::P2tr => "p2tr",
+			AddressKind::Evm => "evm",
+		}
+	}
+
+	/// Address of this kind paying to a public key
+	pub fn target<C: secp256k1::Verification>(self, secp256k1: &secp256k1::Secp256k1<C>, public_key: &CompressedPublicKey, network: Network) -> Target {
+		let address = match self {
+			AddressKind::P2pkh => Address::p2pkh(public_key, network),
+			AddressKind::P2shP2wpkh => Address::p2shwpkh(public_key, network),
+			AddressKind::P2wpkh => Address::p2wpkh(public_key, network),
+			AddressKind::P2tr => Address::p2tr(secp256k1, public_key.0.x_only_public_key().0, None, network),
+			AddressKind::Evm => return Target::Evm(evm_address(public_key)),
+		};
+
+		Target::Bitcoin(address)
+	}
+}
+
+/// An address searched for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+	Bitcoin(Address),
+	Evm(EvmAddress),
+}
+
+impl std::fmt::Display for Target {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Target::Bitcoin(address) => address.fmt(f),
+			Target::Evm(address) => f.write_str(&eip55(address)),
+		}
+	}
+}
+
+/// Keccak-256 of the uncompressed public key without its `0x04` prefix, the last 20 bytes form the address
+pub(crate) fn evm_address(public_key: &CompressedPublicKey) -> EvmAddress {
+	use sha3::Digest;
+
+	let uncompressed = public_key.0.serialize_uncompressed();
+	let hash = sha3::Keccak256::digest(&uncompressed[1..]);
+
+	let mut address = [0; 20];
+	address.copy_from_slice(&hash[12..]);
+	address
+}
+
+/// Mixed case checksum encoding of EIP-55, a hex digit is uppercase if the matching nibble of the address's hash is 8 or more
+pub(crate) fn eip55(address: &EvmAddress) -> String {
+	use sha3::Digest;
+
+	let lowercase = hex::encode(address);
+	let hash = sha3::Keccak256::digest(lowercase.as_bytes());
+
+	let checksummed = lowercase.char_indices().map(|(idx, char)| {
+		let nibble = (hash[idx / 2] >> (4 * (1 - idx % 2))) & 0xF;
+		match nibble >= 8 {
+			true => char.to_ascii_uppercase(),
+			false => char,
+		}
+	});
+
+	format!("0x{}", checksummed.collect::<String>())
+}
+
+impl Target {
+	/// 20 bytes identifying the target: the hash160 of P2PKH, P2SH and P2WPKH outputs, the first 20 bytes of a taproot output key, or an EVM address
+	pub fn fingerprint(&self) -> [u8; 20] {
+		let script = match self {
+			Target::Bitcoin(address) => address.script_pubkey(),
+			Target::Evm(address) => return *address,
+		};
+
+		let start = match script.is_p2pkh() {
+			true => 3,
+			false => 2,
+		};
+
+		let mut fingerprint = [0; 20];
+		fingerprint.copy_from_slice(&script.as_bytes()[start..start + 20]);
+		fingerprint
+	}
+}
+
+/// Addresses searched for, stored as their fingerprints followed by the flag of their address kind.
+/// Entries are sorted, so millions of targets stay compact in memory and on disk
+pub struct Targets {
+	entries: Entries,
+	/// address kinds present among the targets, only these are derived for each candidate
+	kinds: Vec<AddressKind>,
+	/// labels given to targets of an address file, indices don't keep labels
+	labels: gxhash::HashMap<[u8; Targets::ENTRY_SIZE], String>,
+	network: Network,
+}
+
+/// Sorted entries of the targets, read into memory from a file of addresses or mapped from an index
+enum Entries {
+	Owned(Vec<[u8; Targets::ENTRY_SIZE]>),
+	/// the whole index, entries follow its header. Pages are loaded as lookups reach them and may be evicted under memory pressure
+	Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for Entries {
+	type Target = [[u8; Targets::ENTRY_SIZE]];
+
+	fn deref(&self) -> &Self::Target {
+		match self {
+			Entries::Owned(entries) => entries,
+			Entries::Mapped(map) => map[Targets::INDEX_HEADER_SIZE..].as_chunks().0,
+		}
+	}
+}
+
+impl Targets {
+	pub(crate) const ENTRY_SIZE: usize = 21;
+
+	/// Identifies an index written by `Targets::save`
+	const INDEX_MAGIC: &[u8; 8] = b"BIP39IDX";
+	const INDEX_VERSION: u32 = 1;
+
+	/// Magic, version, network magic and entry count
+	const INDEX_HEADER_SIZE: usize = Self::INDEX_MAGIC.len() + 4 + 4 + 8;
+
+	pub fn new<I: IntoIterator<Item = Target>>(targets: I, network: Network) -> Result<Targets, Error> {
+		let mut entries = Vec::new();
+
+		for target in targets {
+			entries.push(Self::entry(&target).map_err(Error::Addresses)?);
+		}
+
+		entries.sort_unstable();
+		entries.dedup();
+
+		Ok(Targets::from_entries(Entries::Owned(entries), network))
+	}
+
+	fn from_entries(entries: Entries, network: Network) -> Targets {
+		let flags = entries.iter().fold(0, |flags, entry| flags | entry[20] as u32);
+		let kinds = [AddressKind::P2pkh, AddressKind::P2shP2wpkh, AddressKind::P2wpkh, AddressKind::P2tr, AddressKind::Evm]
+			.into_iter()
+			.filter(|kind| flags & kind.flag() != 0)
+			.collect();
+
+		Targets {
+			entries,
+			kinds,
+			labels: Default::default(),
+			network,
+		}
+	}
+
+	/// Fingerprint of a target followed by the flag of its address kind
+	pub(crate) fn entry(target: &Target) -> Result<[u8; Self::ENTRY_SIZE], String> {
+		let mut entry = [0; Self::ENTRY_SIZE];
+		entry[..20].copy_from_slice(&target.fingerprint());
+		entry[20] = AddressKind::of(target)?.flag() as u8;
+		Ok(entry)
+	}
+
+	/// Labels targets, such as the wallet an address belongs to
+	pub fn with_labels<I: IntoIterator<Item = (Target, String)>>(mut self, labels: I) -> Result<Targets, Error> {
+		for (target, label) in labels {
+			self.labels.insert(Self::entry(&target).map_err(Error::Addresses)?, label);
+		}
+
+		Ok(self)
+	}
+
+	pub fn label(&self, target: &Target) -> Option<&str> {
+		self.labels.get(&Self::entry(target).ok()?).map(String::as_str)
+	}
+
+	/// Reads the targets from an index written by `Targets::save`, or from a file of addresses, one per line.
+	/// Addresses may be followed by a comma and a label, blank lines are skipped. A malformed line or an unsupported address type fails with its line number,
+	/// build an index to skip them in dumps holding other outputs
+	pub fn read(path: &str, network: Network) -> Result<Targets, Error> {
+		let mut file = std::fs::File::open(path).map_err(|err| {
+			Error::Addresses(format!(
+				"Unable to open `{}`: {}. Create it, containing P2PKH, P2SH-P2WPKH, P2WPKH, P2TR or EVM addresses to test against",
+				path, err
+			))
+		})?;
+
+		// indices are loaded as they are
+		let mut magic = [0; 8];
+		if file.read_exact(&mut magic).is_ok() && Targets::is_index(&magic) {
+			return Targets::load(path, network);
+		}
+
+		file.rewind().map_err(|err| Error::Addresses(format!("Unable to read `{}`: {}", path, err)))?;
+
+		let mut targets = Vec::new();
+		let mut labels = Vec::new();
+
+		for (idx, line) in std::io::BufReader::new(file).lines().enumerate() {
+			let line = line.map_err(|err| Error::Addresses(format!("Unable to read line {} of `{}`: {}", idx + 1, path, err)))?;
+
+			match super::index::parse_line(&line, network) {
+				Some(Ok((target, label))) => {
+					labels.extend(label.map(|label| (target.clone(), label)));
+					targets.push(target);
+				}
+				Some(Err(err)) => return Err(Error::Addresses(format!("Line {} of `{}`: {}", idx + 1, path, err))),
+				None => {}
+			}
+		}
+
+		Targets::new(targets, network)?.with_labels(labels)
+	}
+
+	/// Maps an index written by `Targets::save` into memory, the network of the index must match.
+	/// Entries aren't copied, an index of hundreds of millions of targets is read from the file as lookups reach its pages
+	pub fn load(path: &str, network: Network) -> Result<Targets, Error> {
+		let error = |err: std::io::Error| Error::Addresses(format!("Unable to read index `{}`: {}", path, err));
+		let file = std::fs::File::open(path).map_err(error)?;
+
+		// safety: the index must not be modified while targets are checked against it, as with any file read during a scan
+		let map = unsafe { memmap2::Mmap::map(&file) }.map_err(error)?;
+		if map.len() < Self::INDEX_HEADER_SIZE || !Self::is_index(&map) {
+			return Err(Error::Addresses(format!("`{}` isn't an index of targets", path)));
+		}
+
+		let field = |start: usize, end: usize| &map[Self::INDEX_MAGIC.len() + start..Self::INDEX_MAGIC.len() + end];
+		let version = u32::from_le_bytes(field(0, 4).try_into().unwrap());
+		let magic = bitcoin::p2p::Magic::from_bytes(field(4, 8).try_into().unwrap());
+		let count = u64::from_le_bytes(field(8, 16).try_into().unwrap());
+
+		if version != Self::INDEX_VERSION {
+			return Err(Error::Addresses(format!("Index `{}` has version {}, expected {}", path, version, Self::INDEX_VERSION)));
+		}
+
+		if Network::from_magic(magic) != Some(network) {
+			return Err(Error::Addresses(format!("Index `{}` was built for a different network than {}", path, network)));
+		}
+
+		if count.checked_mul(Self::ENTRY_SIZE as u64).and_then(|size| size.checked_add(Self::INDEX_HEADER_SIZE as u64)) != Some(map.len() as u64) {
+			return Err(Error::Addresses(format!("Index `{}` is truncated, expected {} entries", path, count)));
+		}
+
+		let entries = Entries::Mapped(map);
+		if !entries.is_sorted() {
+			return Err(Error::Addresses(format!("Entries of index `{}` aren't sorted", path)));
+		}
+
+		Ok(Targets::from_entries(entries, network))
+	}
+
+	/// Writes the targets as an index: magic, version, network magic and entry count, followed by the sorted entries
+	pub fn save(&self, path: &str) -> Result<(), Error> {
+		Self::write_index(path, self.network, self.entries.iter().map(|entry| Ok(*entry))).map(drop)
+	}
+
+	/// Writes sorted entries without duplicates as an index, returning their count. The count in the header is filled in once every entry is written
+	pub(crate) fn write_index<I: IntoIterator<Item = std::io::Result<[u8; Self::ENTRY_SIZE]>>>(path: &str, network: Network, entries: I) -> Result<u64, Error> {
+		let write = || -> std::io::Result<u64> {
+			let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+			file.write_all(Self::INDEX_MAGIC)?;
+			file.write_all(&Self::INDEX_VERSION.to_le_bytes())?;
+			file.write_all(&network.magic().to_bytes())?;
+			file.write_all(&0u64.to_le_bytes())?;
+
+			let mut count = 0u64;
+			for entry in entries {
+				file.write_all(&entry?)?;
+				count += 1;
+			}
+
+			file.seek(std::io::SeekFrom::Start(Self::INDEX_HEADER_SIZE as u64 - 8))?;
+			file.write_all(&count.to_le_bytes())?;
+			file.flush()?;
+			Ok(count)
+		};
+
+		write().map_err(|err| Error::Addresses(format!("Unable to write index `{}`: {}", path, err)))
+	}
+
+	/// Whether the contents start like an index written by `Targets::save`
+	pub fn is_index(bytes: &[u8]) -> bool {
+		bytes.starts_with(Self::INDEX_MAGIC)
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn kinds(&self) -> &[AddressKind] {
+		&self.kinds
+	}
+
+	pub fn network(&self) -> Network {
+		self.network
+	}
+
+	/// Hash of the entries, identifies what a scan is checked against regardless of the file the targets were read from
+	pub(crate) fn digest(&self) -> sha256::Hash {
+		let mut engine = sha256::Hash::engine();
+		for entry in self.entries.iter() {
+			engine.input(entry);
+		}
+
+		sha256::Hash::from_engine(engine)
+	}
+
+	pub fn contains(&self, target: &Target) -> bool {
+		Self::entry(target).is_ok_and(|entry| self.entries.binary_search(&entry).is_ok())
+	}
+
+	/// Fingerprints of the targets, sorted and without duplicates. A public key whose fingerprint is among these is checked against the exact targets
+	pub(crate) fn fingerprints(&self) -> impl Iterator<Item = &[u8; 20]> + '_ {
+		let mut previous = None;
+
+		self.entries.iter().filter_map(move |entry| {
+			let fingerprint: &[u8; 20] = entry[..20].try_into().unwrap();
+			match previous.replace(fingerprint) == Some(fingerprint) {
+				true => None,
+				false => Some(fingerprint),
+			}
+		})
+	}
+
+	/// Finds an address paying to the public key among the targets
+	pub fn find<C: secp256k1::Verification>(&self, secp256k1: &secp256k1::Secp256k1<C>, public_key: &CompressedPublicKey) -> Option<Target> {
+		self.kinds.iter().map(|kind| kind.target(secp256k1, public_key, self.network)).find(|target| self.contains(target))
+	}
+}
+
+pub fn parse_address(address: &str, network: Network) -> Result<Address, String> {
+	let unchecked: Address<bitcoin::address::NetworkUnchecked> = address.parse().map_err(|err| format!("Invalid address {}: {}", address, err))?;
+	unchecked.require_network(network).map_err(|err| err.to_string())
+}
+
+/// Parses a `0x` prefixed EVM address, mixed case addresses must carry a valid EIP-55 checksum
+pub fn parse_evm_address(address: &str) -> Result<EvmAddress, String> {
+	let Some(digits) = address.strip_prefix("0x") else {
+		return Err(format!("EVM address {} should start with 0x", address));
+	};
+
+	let mut bytes = [0; 20];
+	hex::decode_to_slice(digits, &mut bytes).map_err(|err| format!("Invalid EVM address {}: {}", address, err))?;
+
+	let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+	if mixed_case && eip55(&bytes) != address {
+		return Err(format!("Invalid EIP-55 checksum of EVM address {}", address));
+	}
+
+	Ok(bytes)
+}
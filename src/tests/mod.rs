@@ -33,9 +33,70 @@ fn pbkdf2(bytes: &[u8]) -> [u8; 64] {
 	pbkdf2::pbkdf2_hmac_array::<sha2::Sha512, 64>(bytes, b"mnemonic", 2048)
 }
 
+/// Opens the first usable GPU
+fn init_device() -> (wgpu::Device, wgpu::Queue) {
+	pollster::block_on(device::init_all(wgpu::Backends::all().with_env(), &[])).unwrap().swap_remove(0)
+}
+
+/// Runs the solver on a backend
+fn solve(config: &Config, backend: Backend, targets: &targets::Targets, paths: &paths::Paths, sender: flume::Sender<solver::StageComputation>) {
+	match backend {
+		Backend::Cpu => solver::cpu::solve(config, targets, paths, &solver::Steps::new(config), sender).unwrap(),
+		Backend::Gpu => {
+			let (device, queue) = init_device();
+			solver::solve(config, targets, paths, &device, &queue, &solver::Steps::new(config), 0, sender).unwrap()
+		}
+	}
+}
+
+/// Targets paying to a public key of every seed, rotating through address kinds and paths. Returns the hits expected as (candidate, path)
+fn targets_of<I: IntoIterator<Item = (u64, [u8; 64])>>(seeds: I, paths: &paths::Paths) -> (targets::Targets, std::collections::BTreeSet<(u64, u32)>) {
+	let kinds = [
+		targets::AddressKind::P2pkh,
+		targets::AddressKind::P2shP2wpkh,
+		targets::AddressKind::P2wpkh,
+		targets::AddressKind::P2tr,
+		targets::AddressKind::Evm,
+	];
+	let paths = paths.iter().collect::<Vec<_>>();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	let mut targets = Vec::new();
+	let mut expected = std::collections::BTreeSet::new();
+
+	for (idx, (index, seed)) in seeds.into_iter().enumerate() {
+		let path = idx % paths.len();
+
+		let master = bitcoin::bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &seed).unwrap();
+		let child = master.derive_priv(&secp256k1, &paths[path]).unwrap();
+		let public_key = bitcoin::CompressedPublicKey(child.private_key.public_key(&secp256k1));
+
+		targets.push(kinds[idx % kinds.len()].target(&secp256k1, &public_key, bitcoin::Network::Bitcoin));
+		expected.insert((index, path as u32));
+	}
+
+	(targets::Targets::new(targets, bitcoin::Network::Bitcoin).unwrap(), expected)
+}
+
+/// Hits of every step as (candidate, path), and the number of master extended keys derived
+fn collect_hits(receiver: flume::Receiver<solver::StageComputation>) -> (std::collections::BTreeSet<(u64, u32)>, usize) {
+	let mut hits = std::collections::BTreeSet::new();
+	let mut derived = 0;
+
+	while let Ok(solver::StageComputation { step, derived: count, hits: found, .. }) = receiver.recv() {
+		for hit in found.iter() {
+			assert!(hits.insert((step + hit.offset as u64, hit.path)), "Duplicate Hit: {:?}", hit);
+		}
+
+		derived += count;
+	}
+
+	(hits, derived)
+}
+
 #[test]
 fn verify_filtered_mnemonics() {
-	let stencil = ["elder", "resist", "rocket", "skill", "_", "_", "_", "_", "jungle", "zoo", "circle", "circle"];
+	let stencil = ["_", "resist", "rocket", "skill", "elder", "_", "_", "jungle", "_", "zoo", "circle", "circle"];
 	let config = Config {
 		stencil: stencil.map(|s| s.to_string()).into_iter().collect(),
 		range: (0, 2048),
@@ -43,46 +104,151 @@ fn verify_filtered_mnemonics() {
 	};
 
 	// init devices
-	let (device, queue) = pollster::block_on(device::init());
+	let (device, queue) = init_device();
+	let solver_stencil = solver::stencil::Stencil::parse(stencil.into_iter(), bip39::Language::English).unwrap();
+
+	// every valid candidate is a target, so every one should be hit
+	let paths = paths::Paths::new(&["m/0"]).unwrap();
+	let valid = (0..2048).filter_map(|index| solver_stencil.mnemonic(index, bip39::Language::English).ok().map(|mnemonic| (index, mnemonic.to_seed(""))));
+	let (targets, expected) = targets_of(valid, &paths);
 
 	// start monitoring thread
 	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
 
 	let thread = std::thread::spawn(move || {
-		// verify identity of entropies
-		let mut set = std::collections::BTreeSet::new();
+		let (hits, derived) = collect_hits(receiver);
 
-		// verifies outputs from solver
-		while let Ok(comp) = receiver.recv() {
-			let solver::StageComputation { constants, outputs, .. } = comp;
-
-			// verify constants
-			for output in outputs {
-				let entropy = [constants.word0, constants.word1, output.word2, constants.word3];
-				let entropy_be = entropy.map(|e| e.to_be());
-				let bytes: &[u8] = bytemuck::cast_slice(&entropy_be);
-
-				let mnemonic = bip39::Mnemonic::from_entropy_in(bip39::Language::English, bytes).unwrap();
-				assert_eq!(constants.checksum as u8, mnemonic.checksum(), "Extracted Mnemonic Sequence has invalid checksum");
-
-				// verify stencil
-				mnemonic.words().zip(stencil.iter()).enumerate().for_each(|(idx, (output, stencil))| {
-					if *stencil != "_" {
-						assert_eq!(output, *stencil, "Word[{}] mismatch between Stencil and GPU output", idx);
-					}
-				});
-
-				// verify uniqueness
-				assert!(set.insert(entropy_be.clone()), "Duplicate Entropy Found: {:?}", entropy_be);
-			}
+		// verify checksums and stencil of hits
+		for &(index, _) in hits.iter() {
+			let mnemonic = solver_stencil.mnemonic(index, bip39::Language::English).expect("Extracted Mnemonic Sequence has invalid checksum");
+
+			mnemonic.words().zip(stencil.iter()).enumerate().for_each(|(idx, (output, stencil))| {
+				if *stencil != "_" {
+					assert_eq!(output, *stencil, "Word[{}] mismatch between Stencil and GPU output", idx);
+				}
+			});
 		}
 
 		// ensure set is not empty
-		assert!(!set.is_empty(), "Entropies Set was empty");
+		assert!(!hits.is_empty(), "Entropies Set was empty");
+		assert_eq!(derived, expected.len(), "Filter stage should only derive candidates with a valid checksum");
+		assert_eq!(hits, expected);
 	});
 
-	solver::solve(&config, &device, &queue, sender);
-	let _ = thread.join().unwrap();
+	solver::solve(&config, &targets, &paths, &device, &queue, &solver::Steps::new(&config), 0, sender).unwrap();
+	thread.join().unwrap();
+}
+
+#[test]
+fn verify_filtered_candidate_sets() {
+	let config = Config {
+		stencil: "se* resist rocket skill elder {cat,car,cable} ?oo? jungle j* zoo circle circle".split(' ').map(|s| s.to_string()).collect(),
+		..Default::default()
+	};
+
+	// init devices
+	let (device, queue) = init_device();
+	let stencil = solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), bip39::Language::English).unwrap();
+	let config = Config {
+		range: (0, stencil.space().unwrap()),
+		..config
+	};
+
+	// every candidate with a valid checksum, computed on the CPU
+	let valid = (0..stencil.space().unwrap()).filter(|&index| stencil.mnemonic(index, bip39::Language::English).is_ok()).collect::<Vec<_>>();
+
+	// seeds are slow to derive on the CPU, a sample of the candidates is targeted
+	let paths = paths::Paths::new(&["m/0"]).unwrap();
+	let sample = valid.iter().step_by(16).map(|&index| (index, stencil.mnemonic(index, bip39::Language::English).unwrap().to_seed("")));
+	let (targets, expected) = targets_of(sample, &paths);
+
+	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
+	let thread = std::thread::spawn(move || {
+		let (hits, derived) = collect_hits(receiver);
+
+		assert_eq!(derived, valid.len(), "Filter stage should only enumerate the cartesian product of the candidate sets");
+		assert_eq!(hits, expected);
+	});
+
+	solver::solve(&config, &targets, &paths, &device, &queue, &solver::Steps::new(&config), 0, sender).unwrap();
+	thread.join().unwrap();
+}
+
+#[test]
+fn verify_pipelined_steps() {
+	// four steps cycle through two sets of buffers, the matches and hits of each step must stay with it
+	let config = Config {
+		stencil: "_ _ {abandon,ability,able,about,above,absent,absorb,abstract,absurd,abuse,access,accident,account,accuse,achieve,acid} resist rocket skill elder jungle zoo circle circle return"
+			.split(' ')
+			.map(|s| s.to_string())
+			.collect(),
+		buffers: Some(2),
+		..Default::default()
+	};
+
+	// init devices
+	let (device, queue) = init_device();
+	let stencil = solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), bip39::Language::English).unwrap();
+	let config = Config {
+		range: (0, stencil.space().unwrap()),
+		..config
+	};
+
+	let step = solver::STEP as u64;
+	assert_eq!(config.range.1, 4 * step);
+
+	// the first and last valid candidate of every step are targeted
+	let valid = |index: &u64| stencil.mnemonic(*index, bip39::Language::English).is_ok();
+	let sample = (0..4)
+		.flat_map(|idx| [(idx * step..(idx + 1) * step).find(valid), (idx * step..(idx + 1) * step).rev().find(valid)])
+		.flatten()
+		.map(|index| (index, stencil.mnemonic(index, bip39::Language::English).unwrap().to_seed("")))
+		.collect::<Vec<_>>();
+
+	let paths = paths::Paths::new(&["m/0"]).unwrap();
+	let (targets, expected) = targets_of(sample, &paths);
+
+	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
+	let thread = std::thread::spawn(move || {
+		let mut steps = Vec::new();
+		let mut hits = std::collections::BTreeSet::new();
+
+		while let Ok(computation) = receiver.recv() {
+			steps.push(computation.step);
+			hits.extend(computation.hits.iter().map(|hit| (computation.step + hit.offset as u64, hit.path)));
+		}
+
+		assert_eq!(steps, [0, step, 2 * step, 3 * step], "Steps should be sent once each, in order");
+		assert_eq!(hits, expected);
+	});
+
+	solver::solve(&config, &targets, &paths, &device, &queue, &solver::Steps::new(&config), 0, sender).unwrap();
+	thread.join().unwrap();
+}
+
+#[test]
+fn share_steps_between_devices() {
+	let config = Config {
+		range: (5, 5 + 40 * solver::STEP as u64 + 3),
+		..Default::default()
+	};
+
+	// devices claim steps as they go, every step is claimed exactly once
+	let steps = solver::Steps::new(&config);
+	let mut claimed = std::thread::scope(|scope| {
+		let handles = (0..4).map(|_| scope.spawn(|| std::iter::from_fn(|| steps.next()).collect::<Vec<_>>())).collect::<Vec<_>>();
+		handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+	});
+
+	claimed.sort_unstable();
+	assert_eq!(claimed, (config.range.0..config.range.1).step_by(solver::STEP as _).collect::<Vec<_>>());
+	assert_eq!(steps.next(), None);
+
+	// a stopped range hands out no more steps
+	let steps = solver::Steps::new(&config);
+	assert_eq!(steps.next(), Some(5));
+	steps.stop();
+	assert_eq!(steps.next(), None);
 }
 
 #[test]
@@ -97,118 +263,155 @@ fn extract_derivations() {
 	};
 
 	// init devices
-	let (device, queue) = pollster::block_on(device::init());
+	let (device, queue) = init_device();
 	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
+	let paths = paths::Paths::new(&["m/44'/0'/0'/0/0", "m/84'/0'/0'/0/0"]).unwrap();
+
+	// every valid candidate is a target at one of the paths
+	let stencil = solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), bip39::Language::English).unwrap();
+	let valid = (0..2048).filter_map(|index| stencil.mnemonic(index, bip39::Language::English).ok().map(|mnemonic| (index, mnemonic.to_seed(""))));
+	let (targets, _) = targets_of(valid, &paths);
+	let paths_ = paths.clone();
 
 	let thread = std::thread::spawn(move || {
-		let null_hash: [u32; 64] = bytemuck::Zeroable::zeroed();
-		let derivation_path = bitcoin::bip32::DerivationPath::from_str("m/44'/0'/0'/0/0").unwrap();
 		let secp256k1 = bitcoin::key::Secp256k1::new();
+		let paths = paths.iter().collect::<Vec<_>>();
 
-		// verifies outputs from solver
+		// prints hits from solver
 		while let Ok(comp) = receiver.recv() {
-			let solver::StageComputation { outputs, .. } = comp;
-
-			let then = std::time::Instant::now();
-			let len = outputs.len() as u32;
-
-			for (idx, output) in IntoIterator::into_iter(outputs).enumerate() {
-				assert_ne!(output.hash, null_hash);
-
-				let combined = output.hash.map(|s| s as u8);
-				println!("GpuMasterExtendedKey[{}] = \"{}\"", idx, hex::encode(&combined));
+			let solver::StageComputation { step, hits, .. } = comp;
 
-				let mut private_key_bytes = [0; 32];
-				private_key_bytes.copy_from_slice(&combined[..32]);
-
-				let mut chain_code_bytes = [0; 32];
-				chain_code_bytes.copy_from_slice(&combined[32..]);
-
-				let extended_private_key = bitcoin::bip32::Xpriv {
-					network: bitcoin::NetworkKind::Main,
-					depth: 0,
-					parent_fingerprint: bitcoin::bip32::Fingerprint::from([0; 4]),
-					child_number: bitcoin::bip32::ChildNumber::Hardened { index: 0 },
-					private_key: bitcoin::secp256k1::SecretKey::from_slice(&private_key_bytes).unwrap(),
-					chain_code: bitcoin::bip32::ChainCode::from(chain_code_bytes),
-				};
+			for hit in hits.iter() {
+				let mnemonic = stencil.mnemonic(step + hit.offset as u64, bip39::Language::English).unwrap();
+				println!("Mnemonic[{}] = \"{}\"", hit.offset, mnemonic);
 
 				// derive child private key
-				let child_private_key = extended_private_key.derive_priv(&secp256k1, &derivation_path).unwrap();
-				println!("DerivedPrivateKey = \"{}\"", child_private_key);
+				let master = bitcoin::bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &mnemonic.to_seed("")).unwrap();
+				let child_private_key = master.derive_priv(&secp256k1, &paths[hit.path as usize]).unwrap();
+				println!("DerivedPrivateKey[{}] = \"{}\"", paths[hit.path as usize], child_private_key);
 
 				// derive public key hash
 				let public_key = bitcoin::PublicKey::from_private_key(&secp256k1, &child_private_key.to_priv());
-				let p2pkh = bitcoin::Address::p2pkh(&public_key, bitcoin::Network::Bitcoin);
+				let p2pkh = bitcoin::Address::p2pkh(public_key, bitcoin::Network::Bitcoin);
 				println!("Pay2PublicKeyHash = \"{}\"\n", p2pkh);
 			}
-
-			println!("KeyDerivation took: {:?}", then.elapsed() / len);
 		}
 	});
 
-	solver::solve(&config, &device, &queue, sender);
-	let _ = thread.join().unwrap();
+	solver::solve(&config, &targets, &paths_, &device, &queue, &solver::Steps::new(&config), 0, sender).unwrap();
+	thread.join().unwrap();
 }
 
 #[test]
 fn verify_derived_hashes() {
+	verify_derived_hashes_of(&["return", "jungle", "rocket", "skill", "_", "_", "_", "_", "jungle", "zoo", "circle", "return"], None, Backend::Gpu);
+}
+
+#[test]
+fn verify_derived_hashes_with_passphrase() {
+	// requires NFKD normalization: "ℌ" -> "H", "ö" -> "o\u{308}"
+	verify_derived_hashes_of(
+		&["return", "jungle", "rocket", "skill", "_", "_", "_", "_", "jungle", "zoo", "circle", "return"],
+		Some("ℌello Wörld"),
+		Backend::Gpu,
+	);
+}
+
+#[test]
+fn verify_derived_hashes_24_words() {
+	verify_derived_hashes_of(
+		&[
+			"return", "jungle", "rocket", "skill", "elder", "resist", "word", "wire", "either", "zoo", "circle", "return", "setup", "arrange", "_", "foam", "jelly", "word", "wire", "either", "other", "oblige",
+			"cupboard", "_",
+		],
+		None,
+		Backend::Gpu,
+	);
+}
+
+#[test]
+fn verify_derived_hashes_japanese() {
+	// a single argument separated by ideographic spaces, as japanese mnemonics are displayed
+	let mnemonic = bip39::Mnemonic::from_entropy_in(bip39::Language::Japanese, &[0x7f; 32]).unwrap();
+	let sentence = mnemonic.words().enumerate().map(|(idx, w)| if idx == 3 { "_" } else { w }).collect::<Vec<_>>().join("\u{3000}");
+
+	verify_derived_hashes_of(&[&sentence], None, Backend::Gpu);
+}
+
+#[test]
+fn verify_cpu_derived_hashes() {
+	verify_derived_hashes_of(
+		&["return", "jungle", "rocket", "skill", "_", "_", "_", "_", "jungle", "zoo", "circle", "return"],
+		Some("ℌello Wörld"),
+		Backend::Cpu,
+	);
+}
+
+#[test]
+fn verify_cpu_derived_hashes_japanese() {
+	let mnemonic = bip39::Mnemonic::from_entropy_in(bip39::Language::Japanese, &[0x7f; 32]).unwrap();
+	let sentence = mnemonic.words().enumerate().map(|(idx, w)| if idx == 23 { "_" } else { w }).collect::<Vec<_>>().join("\u{3000}");
+
+	verify_derived_hashes_of(&[&sentence], None, Backend::Cpu);
+}
+
+fn verify_derived_hashes_of(stencil: &[&str], passphrase: Option<&str>, backend: Backend) {
 	let config = Config {
-		stencil: ["return", "jungle", "rocket", "skill", "_", "_", "_", "_", "jungle", "zoo", "circle", "return"]
-			.map(|s| s.to_string())
-			.into_iter()
-			.collect(),
+		stencil: stencil.iter().map(|s| s.to_string()).collect(),
 		range: (0, 2048),
+		passphrase: passphrase.map(|p| p.to_string()),
 		..Default::default()
 	};
 
-	// init devices
-	let (device, queue) = pollster::block_on(device::init());
+	let language = solver::stencil::language(&config).unwrap();
+	let stencil = solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language).unwrap();
+	let passphrase = config.passphrase.clone();
 
-	// start monitoring thread
-	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
+	// an unknown checksum word only enumerates its entropy bits, which may leave fewer candidates
+	let config = Config {
+		range: (0, stencil.space().unwrap().min(2048)),
+		..config
+	};
 
-	let thread = std::thread::spawn(move || {
-		// verify hash of entropies
-		let mut processed = 0;
-		let null_hash: [u32; 64] = bytemuck::Zeroable::zeroed();
+	// hardened and normal levels, with paths sharing a parent
+	let paths = paths::Paths::new(&["m/44'/0'/0'/0/0", "m/84'/0'/0'/{0,1}/7", "m/0/1h"]).unwrap();
 
-		// verifies outputs from solver
-		while let Ok(comp) = receiver.recv() {
-			let solver::StageComputation { constants, outputs, .. } = comp;
+	// seeds of every valid candidate on the CPU, each is a target at one path
+	let seeds = (config.range.0..config.range.1).filter_map(|index| {
+		let mnemonic = stencil.mnemonic(index, language).ok()?;
 
-			for (idx, output) in outputs.iter().enumerate() {
-				assert_ne!(output.hash, null_hash);
-				let gpu_master_extended_key = output.hash.map(|s| s as u8);
+		let first = mnemonic.words().next().unwrap().to_string();
+		let sequence = mnemonic.words().skip(1).fold(first, |acc, nxt| acc + " " + nxt);
 
-				// verify hmac
-				let entropy = [constants.word0, constants.word1, output.word2, constants.word3];
-				let entropy = entropy.map(|e| e.to_be()); // reverse endianness from insertion
-				let mnemonic = bip39::Mnemonic::from_entropy_in(bip39::Language::English, bytemuck::cast_slice(&entropy)).unwrap();
+		let seed = match passphrase.as_deref() {
+			Some(passphrase) => mnemonic.to_seed(passphrase),
+			None => pbkdf2(sequence.as_bytes()),
+		};
 
-				let first = mnemonic.words().next().unwrap().to_string();
-				let sequence = mnemonic.words().skip(1).fold(first, |acc, nxt| acc + " " + nxt);
+		// debug points
+		println!("Sequence[{}] = \"{}\"", index, sequence);
+		println!("CpuBip39Seed = {}", hex::encode(seed));
+		println!("CpuMasterExtendedKey = {}\n", hex::encode(hmac_sha512(&seed, b"Bitcoin seed")));
 
-				let seed = pbkdf2(sequence.as_bytes());
-				let cpu_master_extended_key = hmac_sha512(&seed, b"Bitcoin seed");
+		Some((index, seed))
+	});
 
-				// debug points
-				println!("Sequence[{}] = \"{}\"", idx, sequence);
-				println!("CpuBip39Seed = {}", hex::encode(&seed));
-				println!("CpuMasterExtendedKey = {}", hex::encode(&cpu_master_extended_key));
-				println!("GpuMasterExtendedKey = {}\n", hex::encode(&gpu_master_extended_key));
+	let (targets, expected) = targets_of(seeds, &paths);
+	assert!(!expected.is_empty(), "No Hashes Were Processed");
 
-				assert_eq!(gpu_master_extended_key, cpu_master_extended_key);
-			}
+	// start monitoring thread
+	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
 
-			processed += 1;
-		}
+	let thread = std::thread::spawn(move || {
+		let (hits, derived) = collect_hits(receiver);
 
-		assert!(processed > 0, "No Hashes Were Processed");
+		// every candidate's public key, address kind and path matches, and no other path does
+		assert_eq!(derived, expected.len());
+		assert_eq!(hits, expected);
 	});
 
-	solver::solve(&config, &device, &queue, sender);
-	let _ = thread.join().unwrap();
+	solve(&config, backend, &targets, &paths, sender);
+	thread.join().unwrap();
 }
 
 #[test]
@@ -219,7 +422,7 @@ fn test_short256() {
 	let inputs = [[12, 23, 45, 65], [00, 00, 00, 00], [16, 76, 89, 12], [255, 255, 255, 255u32]];
 
 	// create device
-	let (device, queue) = pollster::block_on(device::init());
+	let (device, queue) = init_device();
 
 	// prepare layout descriptor
 	let kibbles_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -292,7 +495,7 @@ fn test_short256() {
 	// configure pipeline layout
 	let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 		label: Some("test-short256::pipeline_layout"),
-		bind_group_layouts: &[&bind_group_layout],
+		bind_group_layouts: &[Some(&bind_group_layout)],
 		immediate_size: 0,
 	});
 
@@ -344,7 +547,7 @@ fn test_short256() {
 	});
 
 	// wait for tasks to finish
-	device.poll(wgpu::PollType::Wait).unwrap();
+	device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
 }
 
 #[test]
@@ -377,7 +580,7 @@ fn test_pbkdf2() {
 	});
 
 	// create device
-	let (device, queue) = pollster::block_on(device::init());
+	let (device, queue) = init_device();
 
 	// prepare layout descriptor
 	let inputs_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -450,7 +653,7 @@ fn test_pbkdf2() {
 	// configure pipeline layout
 	let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 		label: Some("test-sha512::pipeline-layout"),
-		bind_group_layouts: &[&bind_group_layout],
+		bind_group_layouts: &[Some(&bind_group_layout)],
 		immediate_size: 0,
 	});
 
@@ -487,7 +690,7 @@ fn test_pbkdf2() {
 	queue.submit([commands]);
 
 	// wait for tasks to finish
-	device.poll(wgpu::PollType::Wait).unwrap();
+	device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
 
 	// read outputs buffer
 	output_buffer.clone().map_async(wgpu::MapMode::Read, .., move |res| {
@@ -505,5 +708,801 @@ fn test_pbkdf2() {
 	});
 
 	// wait for tasks to finish
-	device.poll(wgpu::PollType::Wait).unwrap();
+	device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).unwrap();
+}
+
+#[test]
+fn resolve_stencil_indices() {
+	let word_list = bip39::Language::English.word_list();
+
+	for length in solver::stencil::MNEMONIC_LENGTHS {
+		let entropy = (0..length * 4 / 3).map(|i| (i * 37 + 11) as u8).collect::<Vec<_>>();
+		let mnemonic = bip39::Mnemonic::from_entropy(&entropy).unwrap();
+
+		// unknown words at arbitrary positions, including the checksum word
+		let unknown = [0, 5, 8, length - 1];
+		let words = mnemonic.words().enumerate().map(|(idx, w)| if unknown.contains(&idx) { "_" } else { w }).collect::<Vec<_>>();
+
+		let stencil = solver::stencil::Stencil::parse(words.into_iter(), bip39::Language::English).unwrap();
+		let checksum_bits = length / 3;
+		assert_eq!(stencil.space(), Some(2u64.pow(44 - checksum_bits as u32)));
+
+		// the last unknown word is least significant, only the entropy bits of the checksum word are enumerated
+		let index = unknown.iter().fold(0u64, |acc, &idx| {
+			let word = mnemonic.words().nth(idx).unwrap();
+			let index = word_list.iter().position(|w| *w == word).unwrap() as u64;
+			match idx == length - 1 {
+				true => (acc << (11 - checksum_bits)) | (index >> checksum_bits),
+				false => (acc << 11) | index,
+			}
+		});
+
+		assert_eq!(stencil.mnemonic(index, bip39::Language::English).unwrap(), mnemonic);
+		assert!(stencil.mnemonic(index ^ 1, bip39::Language::English).is_ok(), "Every candidate should have its checksum computed");
+	}
+}
+
+#[test]
+fn resolve_stencil_candidate_sets() {
+	let phrase = "setup arrange elevator foam jelly word wire either other oblige cupboard almost";
+	let stencil = solver::stencil::Stencil::parse(
+		"se* arrange {cat,elevator,car} foam jelly w??d wire either other oblige cupboard {almost}".split(' '),
+		bip39::Language::English,
+	)
+	.unwrap();
+
+	let word_list = bip39::Language::English.word_list();
+	let sets = [
+		word_list.iter().filter(|w| w.starts_with("se")).copied().collect::<Vec<_>>(),
+		vec!["car", "cat", "elevator"],
+		word_list.iter().filter(|w| w.len() == 4 && w.starts_with('w') && w.ends_with('d')).copied().collect::<Vec<_>>(),
+	];
+
+	assert_eq!(stencil.header.unknown_count, 3);
+	assert_eq!(stencil.space(), Some(sets.iter().map(|s| s.len() as u64).product()));
+
+	// every candidate belongs to the cartesian product of the sets, the phrase is enumerated exactly once
+	let candidates = (0..stencil.space().unwrap()).map(|index| stencil.indices(index)).collect::<Vec<_>>();
+	for indices in candidates.iter() {
+		for (set, position) in sets.iter().zip([0, 2, 5]) {
+			assert!(set.contains(&word_list[indices[position] as usize]));
+		}
+	}
+
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, phrase).unwrap();
+	let expected = mnemonic.words().map(|w| word_list.iter().position(|x| *x == w).unwrap() as u32).collect::<Vec<_>>();
+	assert_eq!(candidates.iter().filter(|indices| indices[..12] == expected[..]).count(), 1);
+
+	assert!(solver::stencil::Stencil::parse("qq* _ _ _ _ _ _ _ _ _ _ _".split(' '), bip39::Language::English).is_err());
+	assert!(solver::stencil::Stencil::parse("{cat,qqq} _ _ _ _ _ _ _ _ _ _ _".split(' '), bip39::Language::English).is_err());
+}
+
+#[test]
+fn detect_stencil_language() {
+	let detect = |sentence: &str| solver::stencil::detect_language(sentence.split(' '));
+
+	assert_eq!(detect("_ resist rocket skill elder _ _ jungle _ zoo circle circle"), Ok(bip39::Language::English));
+	assert_eq!(detect("_ _ _ _ _ _ _ _ _ _ _ _"), Ok(bip39::Language::English));
+	assert_eq!(detect("ábaco _ _ _ _ _ _ _ _ _ _ _"), Ok(bip39::Language::Spanish));
+	assert_eq!(detect("あいこくしん _ _ _ _ _ _ _ _ _ _ _"), Ok(bip39::Language::Japanese));
+
+	// shared between the english and french word lists
+	assert!(detect("animal _ _ _ _ _ _ _ _ _ _ _").is_err());
+	assert!(detect("animal bitcoin _ _ _ _ _ _ _ _ _ _").is_err());
+
+	assert_eq!(parse_language("chinese-simplified"), Ok(bip39::Language::SimplifiedChinese));
+	assert_eq!(parse_language("Japanese"), Ok(bip39::Language::Japanese));
+	assert!(parse_language("klingon").is_err());
+}
+
+#[test]
+fn normalized_word_lists_fit_word_budget() {
+	for language in bip39::Language::ALL {
+		for word in language.word_list() {
+			let normalized = solver::types::Bip39Word::new(word);
+			assert!(normalized.length as usize <= solver::types::Bip39Word::MAX_LENGTH, "{} word {} is too long", language, word);
+		}
+	}
+}
+
+#[test]
+fn match_address_kinds() {
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &format!("{} about", ["abandon"; 11].join(" "))).unwrap();
+	let master = bitcoin::bip32::Xpriv::new_master(bitcoin::NetworkKind::Main, &mnemonic.to_seed("")).unwrap();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	// test vectors from BIP44, BIP49, BIP84 and BIP86
+	let vectors = [
+		("m/44'/0'/0'/0/0", "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+		("m/49'/0'/0'/0/0", "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"),
+		("m/84'/0'/0'/0/0", "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+		("m/86'/0'/0'/0/0", "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"),
+	];
+
+	let addresses = vectors
+		.iter()
+		.map(|(_, address)| targets::Target::Bitcoin(targets::parse_address(address, bitcoin::Network::Bitcoin).unwrap()));
+	let targets = targets::Targets::new(addresses, bitcoin::Network::Bitcoin).unwrap();
+
+	for (path, address) in vectors {
+		let path = bitcoin::bip32::DerivationPath::from_str(path).unwrap();
+		let child = master.derive_priv(&secp256k1, &path).unwrap();
+		let public_key = bitcoin::CompressedPublicKey::from_private_key(&secp256k1, &child.to_priv()).unwrap();
+
+		assert_eq!(targets.find(&secp256k1, &public_key).map(|a| a.to_string()).as_deref(), Some(address));
+	}
+
+	// fingerprints matched on the GPU: a public key, script or witness program hash, or the start of a taproot output key
+	let fingerprints = targets.fingerprints().collect::<Vec<_>>();
+	assert!(fingerprints.is_sorted());
+	assert_eq!(fingerprints.len(), vectors.len());
+
+	for (_, address) in vectors {
+		let address = targets::parse_address(address, bitcoin::Network::Bitcoin).unwrap();
+		let fingerprint = match (address.pubkey_hash(), address.script_hash(), address.witness_program()) {
+			(Some(hash), _, _) => hash[..].to_vec(),
+			(_, Some(hash), _) => hash[..].to_vec(),
+			(_, _, Some(program)) => program.program().as_bytes()[..20].to_vec(),
+			_ => unreachable!(),
+		};
+
+		assert!(fingerprints.iter().any(|f| f[..] == fingerprint[..]), "Missing fingerprint of {}", address);
+	}
+
+	assert!(targets::parse_address("tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl", bitcoin::Network::Bitcoin).is_err());
+}
+
+#[test]
+fn filter_fingerprints() {
+	use solver::passes::public_key::Bloom;
+
+	// pseudo random fingerprints, as uniform as hashes
+	let mut state = 0x9e3779b97f4a7c15u64;
+	let mut fingerprint = || -> [u8; 20] {
+		std::array::from_fn(|_| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state as u8
+		})
+	};
+
+	// sized by the number of fingerprints when the device has room, and to the device otherwise
+	assert_eq!(Bloom::new(0, 1 << 30), Bloom { bits: 32, hashes: 1 });
+	assert_eq!(
+		Bloom::new(1000, 1 << 30),
+		Bloom {
+			bits: 1 << 16,
+			hashes: Bloom::MAX_HASHES
+		}
+	);
+	assert_eq!(Bloom::new(300_000_000, 128 << 20), Bloom { bits: 1 << 30, hashes: 2 });
+	assert_eq!(Bloom::new(300_000_000, u64::MAX).bits, 1 << 32);
+	assert_eq!(Bloom::new(1000, 1000).bits, 1 << 12);
+
+	for (fingerprints, limit) in [(10_000, 1 << 30), (10_000, 4096)] {
+		let bloom = Bloom::new(fingerprints, limit);
+		let mut filter = vec![0u32; (bloom.size() / 4) as usize];
+
+		let targets = (0..fingerprints).map(|_| fingerprint()).collect::<Vec<_>>();
+		for target in targets.iter() {
+			bloom.insert(&mut filter, target);
+		}
+
+		// every target passes, others only as often as the expected false positive rate
+		assert!(targets.iter().all(|target| bloom.contains(&filter, target)));
+
+		let samples = 100_000;
+		let false_positives = (0..samples).filter(|_| bloom.contains(&filter, &fingerprint())).count();
+		let expected = bloom.false_positive_rate(fingerprints) * samples as f64;
+		assert!((false_positives as f64) < expected * 2.0 + 10.0, "{} false positives, expected {}", false_positives, expected);
+	}
+}
+
+#[test]
+fn match_test_network_addresses() {
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &format!("{} about", ["abandon"; 11].join(" "))).unwrap();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	for (network, address) in [
+		(bitcoin::Network::Testnet, "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl"),
+		(bitcoin::Network::Signet, "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl"),
+		(bitcoin::Network::Regtest, "bcrt1q6rz28mcfaxtmd6v789l9rrlrusdprr9pz3cppk"),
+	] {
+		let master = bitcoin::bip32::Xpriv::new_master(network, &mnemonic.to_seed("")).unwrap();
+		assert!(master.to_string().starts_with("tprv"));
+
+		// the standard path uses the test networks' coin type
+		let targets = targets::Targets::new([targets::Target::Bitcoin(targets::parse_address(address, network).unwrap())], network).unwrap();
+		let paths = paths::Paths::standard(targets.kinds(), network);
+
+		let mut matched = None;
+		paths.derive(&secp256k1, &master, |path, key| {
+			let public_key = bitcoin::CompressedPublicKey::from_private_key(&secp256k1, &key.to_priv()).unwrap();
+			matched = targets.find(&secp256k1, &public_key).map(|address| (path.to_string(), address.to_string()));
+			matched.is_some()
+		});
+
+		assert_eq!(matched, Some(("84'/1'/0'/0/0".to_string(), address.to_string())));
+		assert!(targets::parse_address(address, bitcoin::Network::Bitcoin).is_err());
+	}
+
+	assert_eq!(parse_network("mainnet"), Ok(bitcoin::Network::Bitcoin));
+	assert_eq!(parse_network("regtest"), Ok(bitcoin::Network::Regtest));
+	assert!(parse_network("litecoin").is_err());
+}
+
+#[test]
+fn match_evm_addresses() {
+	// test vectors from EIP-55
+	for address in [
+		"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+		"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+		"0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+		"0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+	] {
+		let bytes = targets::parse_evm_address(address).unwrap();
+		assert_eq!(targets::eip55(&bytes), address);
+		assert!(targets::parse_evm_address(&address.to_lowercase()).is_ok());
+	}
+
+	assert!(targets::parse_evm_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err(), "Invalid checksum should be rejected");
+	assert!(targets::parse_evm_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae").is_err());
+
+	// the first MetaMask account of the all-abandon mnemonic
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &format!("{} about", ["abandon"; 11].join(" "))).unwrap();
+	let master = bitcoin::bip32::Xpriv::new_master(bitcoin::NetworkKind::Main, &mnemonic.to_seed("")).unwrap();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	let address = "0x9858EfFD232B4033E47d90003D41EC34EcaEda94";
+	let targets = targets::Targets::new([targets::Target::Evm(targets::parse_evm_address(address).unwrap())], bitcoin::Network::Bitcoin).unwrap();
+	assert_eq!(paths::Paths::standard(targets.kinds(), bitcoin::Network::Bitcoin), paths::Paths::new(&["m/44'/60'/0'/0/0"]).unwrap());
+
+	let mut matched = None;
+	paths::Paths::new(&["evm"]).unwrap().derive(&secp256k1, &master, |path, key| {
+		let public_key = bitcoin::CompressedPublicKey::from_private_key(&secp256k1, &key.to_priv()).unwrap();
+		matched = targets.find(&secp256k1, &public_key).map(|target| (path.to_string(), target.to_string()));
+		matched.is_some()
+	});
+
+	assert_eq!(matched, Some(("44'/60'/0'/0/0".to_string(), address.to_string())));
+}
+
+#[test]
+fn expand_derivation_paths() {
+	let paths = paths::Paths::new(&["m/84'/0'/0'/{0,1}/0..20", "m/0h/0h/{1..3,7}h"]).unwrap();
+	assert_eq!(paths.len(), 2 * 20 + 3);
+
+	// every expanded path is derived, parents are shared
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &format!("{} about", ["abandon"; 11].join(" "))).unwrap();
+	let master = bitcoin::bip32::Xpriv::new_master(bitcoin::NetworkKind::Main, &mnemonic.to_seed("")).unwrap();
+	let secp256k1 = bitcoin::key::Secp256k1::new();
+
+	let mut derived = Vec::new();
+	paths.derive(&secp256k1, &master, |path, key| {
+		assert_eq!(master.derive_priv(&secp256k1, &path).unwrap(), key);
+		derived.push(path.to_string());
+		false
+	});
+
+	assert_eq!(derived.len(), paths.len());
+	assert_eq!(derived[0], "84'/0'/0'/0/0");
+	assert_eq!(derived[39], "84'/0'/0'/1/19");
+	assert_eq!(derived[42], "0'/0'/7'");
+
+	// standard paths of each address kind
+	let kinds = [targets::AddressKind::P2pkh, targets::AddressKind::P2tr];
+	assert_eq!(paths::Paths::standard(&kinds, bitcoin::Network::Bitcoin), paths::Paths::new(&["m/44'/0'/0'/0/0", "m/86'/0'/0'/0/0"]).unwrap());
+	assert_eq!(paths::Paths::standard(&kinds, bitcoin::Network::Signet), paths::Paths::new(&["m/44'/1'/0'/0/0", "m/86'/1'/0'/0/0"]).unwrap());
+
+	// duplicated paths are derived once, up to MAX_PATHS distinct parents
+	assert_eq!(paths::Paths::new(&["m/0/{1,1,2}", "m/0/2", "m/1/2"]).unwrap().len(), 3);
+	assert_eq!(paths::Paths::new(&["m/0..65536/0", "m/0..65536/0"]).unwrap().len(), paths::MAX_PATHS);
+	assert!(paths::Paths::new(&["m/0..65536/0", "m/0/1"]).is_err());
+
+	for invalid in ["84'/0'", "m/0..0", "m/{0,x}", "m/2147483648", "m", "m/0..100000", "m/0/0/0/0/0/0/0/0/0"] {
+		assert!(paths::Paths::new(&[invalid]).is_err(), "{} should be invalid", invalid);
+	}
+}
+
+#[test]
+fn resume_from_checkpoint() {
+	let directory = std::env::temp_dir().join(format!("webgpu-bip39-checkpoint-{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+
+	let addresses = directory.join("addresses.txt");
+	std::fs::write(&addresses, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA\n").unwrap();
+
+	let targets = targets::Targets::read(addresses.to_str().unwrap(), bitcoin::Network::Bitcoin).unwrap();
+	let job = Job::builder("_ resist rocket skill elder _ _ jungle _ zoo circle circle".split(' '))
+		.range(0, 2048)
+		.targets(targets)
+		.build()
+		.unwrap();
+	let config = job.config.clone();
+
+	// saved progress is restored as is
+	let fingerprint = job.fingerprint().unwrap();
+	let path = directory.join("checkpoint.txt");
+	let path = path.to_str().unwrap();
+
+	let saved = checkpoint::Checkpoint {
+		fingerprint: fingerprint.clone(),
+		next: 1024,
+		devices: vec![3, 5],
+	};
+	saved.save(path).unwrap();
+	assert_eq!(checkpoint::Checkpoint::load(path).unwrap(), saved);
+
+	let resumed = job.resume(&saved).unwrap().unwrap();
+	assert_eq!(resumed.range(), (1024, 2048));
+	assert!(job.resume(&checkpoint::Checkpoint { next: 2048, ..saved.clone() }).unwrap().is_none());
+
+	// any change to the job parameters changes the fingerprint
+	assert_eq!(checkpoint::fingerprint(&config, &job.targets).unwrap(), fingerprint);
+	assert_ne!(checkpoint::fingerprint(&Config { range: (0, 4096), ..config.clone() }, &job.targets).unwrap(), fingerprint);
+	assert_ne!(
+		checkpoint::fingerprint(
+			&Config {
+				passphrase: Some("x".to_string()),
+				..config.clone()
+			},
+			&job.targets
+		)
+		.unwrap(),
+		fingerprint
+	);
+
+	std::fs::write(&addresses, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA\n37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf\n").unwrap();
+	let targets = targets::Targets::read(addresses.to_str().unwrap(), bitcoin::Network::Bitcoin).unwrap();
+	assert_ne!(checkpoint::fingerprint(&config, &targets).unwrap(), fingerprint);
+	assert!(resumed.resume(&saved).is_err(), "Checkpoints of other jobs should be rejected");
+
+	// a malformed line fails rather than silently checking fewer targets
+	std::fs::write(&addresses, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA\n\n1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabB\n").unwrap();
+	let error = targets::Targets::read(addresses.to_str().unwrap(), bitcoin::Network::Bitcoin).err().unwrap();
+	assert!(matches!(&error, Error::Addresses(message) if message.starts_with("Line 3 of")), "{}", error);
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn run_library_job() {
+	let stencil = format!("{} {{about,above,absent}}", ["abandon"; 11].join(" "));
+	let target = targets::Target::Bitcoin(targets::parse_address("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", bitcoin::Network::Bitcoin).unwrap());
+	let labelled = || {
+		targets::Targets::new([target.clone()], bitcoin::Network::Bitcoin)
+			.unwrap()
+			.with_labels([(target.clone(), "wallet".to_string())])
+			.unwrap()
+	};
+
+	// configs are verified when built, failing with the kind of error the command line exits with
+	let error = Job::builder(["abandon"; 11]).language(bip39::Language::English).targets(labelled()).build().err().unwrap();
+	assert!(matches!(error, Error::Stencil(_)));
+	assert_eq!(error.exit_code(), 2);
+
+	assert!(
+		matches!(Job::builder(stencil.split(' ')).build(), Err(Error::Config { field: "Config", .. })),
+		"A job without targets should be rejected"
+	);
+	assert!(matches!(
+		Job::builder(stencil.split(' ')).path("m/84'/x").targets(labelled()).build(),
+		Err(Error::Config { field: "Path", .. })
+	));
+	assert!(matches!(Job::builder(stencil.split(' ')).range(3, 9).targets(labelled()).build(), Err(Error::Config { field: "Range", .. })));
+	assert!(matches!(Job::builder(stencil.split(' ')).buffers(0).targets(labelled()).build(), Err(Error::Config { field: "Buffers", .. })));
+	assert!(matches!(targets::Targets::read("missing-addresses.txt", bitcoin::Network::Bitcoin), Err(Error::Addresses(_))));
+	assert!(matches!(checkpoint::Checkpoint::load("missing-checkpoint.txt"), Err(Error::Checkpoint(_))));
+
+	// standard paths of the targets' address kinds are checked by default
+	let job = Job::builder([stencil.as_str()]).targets(labelled()).build().unwrap();
+	assert_eq!(job.range(), (0, 3));
+	assert_eq!(job.paths().iter().map(|path| path.to_string()).collect::<Vec<_>>(), ["84'/0'/0'/0/0"]);
+
+	let mut matches = Vec::new();
+	let mut progress = Vec::new();
+
+	let solver = pollster::block_on(Solver::new(Backend::Cpu)).unwrap().queue_depth(1);
+	solver
+		.run(&job, |event| match event {
+			Event::Match(found) => matches.push(found),
+			Event::Progress(update) => progress.push(update),
+		})
+		.unwrap();
+
+	assert_eq!(progress.last().map(|update| (update.device, update.next, update.completed, update.steps)), Some((0, 3, 1, 1)));
+	assert_eq!(matches.len(), 1);
+
+	let record = matches[0].record();
+	assert_eq!(record.mnemonic, format!("{} about", ["abandon"; 11].join(" ")));
+	assert_eq!((record.path.as_str(), record.address_type.as_str(), record.label.as_str()), ("m/84'/0'/0'/0/0", "p2wpkh", "wallet"));
+	assert_eq!((record.candidate, record.range), (0, (0, 3)));
+}
+
+#[test]
+fn interrupt_job() {
+	let stencil = format!("{} {{about,above,absent}}", ["abandon"; 11].join(" "));
+	let target = targets::Target::Bitcoin(targets::parse_address("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", bitcoin::Network::Bitcoin).unwrap());
+	let job = Job::builder([stencil.as_str()]).targets(targets::Targets::new([target], bitcoin::Network::Bitcoin).unwrap()).build().unwrap();
+
+	// claimed steps are still checked, no more are handed out once interrupted
+	let interrupt = Interrupt::default();
+	let steps = solver::Steps::new(&job.config).interrupted_by(&interrupt);
+	assert_eq!(steps.next(), Some(0));
+	interrupt.interrupt();
+	assert_eq!(steps.next(), None);
+
+	// an interrupt after the last step doesn't fail the job
+	let solver = pollster::block_on(Solver::new(Backend::Cpu)).unwrap();
+	let mut events = 0;
+	solver
+		.run(&job, |_| {
+			solver.interrupt().interrupt();
+			events += 1;
+		})
+		.unwrap();
+	assert_eq!(events, 2);
+
+	// clones share the interrupt, a job interrupted before its first step covers none of its range
+	let error = solver.clone().run(&job, |_| panic!("No steps should be checked")).err().unwrap();
+	assert!(matches!(&error, Error::Interrupted { covered } if covered.is_empty()), "{}", error);
+	assert_eq!(error.exit_code(), 130);
+
+	// a worker covers the leases it checked, other workers check the gaps between them
+	let mut covered = Vec::new();
+	for range in [(30, 40), (0, 10), (10, 20), (50, 50), (35, 45)] {
+		distributed::cover(&mut covered, range);
+	}
+	assert_eq!(covered, [(0, 20), (30, 45)]);
+	assert_eq!(Error::Interrupted { covered }.to_string(), "Interrupted, candidates in [0, 20), [30, 45) were checked");
+}
+
+#[test]
+fn serve_metrics() {
+	use std::io::{Read, Write};
+
+	let stencil = format!("{} {{about,above,absent}}", ["abandon"; 11].join(" "));
+	let target = targets::Target::Bitcoin(targets::parse_address("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", bitcoin::Network::Bitcoin).unwrap());
+	let job = Job::builder([stencil.as_str()]).targets(targets::Targets::new([target], bitcoin::Network::Bitcoin).unwrap()).build().unwrap();
+
+	let mut metrics = metrics::Metrics::new(&job);
+	assert_eq!(metrics.eta(), None);
+
+	// filtered and derived candidates are counted apart, the range is covered after the only step
+	metrics.record(&Progress {
+		device: 1,
+		next: 3,
+		completed: 1,
+		steps: 1,
+		filtered: 3,
+		derived: 1,
+	});
+	metrics.record_match();
+
+	assert_eq!(metrics.totals(), (3, 1));
+	assert!(metrics.rates().0 >= metrics.rates().1);
+	assert_eq!(metrics.eta(), Some(std::time::Duration::ZERO));
+
+	let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	metrics::serve(listener, std::sync::Arc::new(std::sync::Mutex::new(metrics)));
+
+	let scrape = |path: &str| {
+		let mut stream = std::net::TcpStream::connect(address).unwrap();
+		stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).unwrap();
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	};
+
+	let response = scrape("/metrics");
+	assert!(response.starts_with("HTTP/1.1 200 OK"));
+	for line in [
+		"bip39_candidates_total{stage=\"filter\"} 3",
+		"bip39_candidates_total{stage=\"derivation\"} 1",
+		"bip39_range_candidates 3",
+		"bip39_matches_total 1",
+		"bip39_device_steps_total{device=\"0\"} 0",
+		"bip39_device_steps_total{device=\"1\"} 1",
+		"# TYPE bip39_candidates_per_second gauge",
+	] {
+		assert!(response.lines().any(|l| l == line), "missing `{}` in:\n{}", line, response);
+	}
+
+	assert!(scrape("/").starts_with("HTTP/1.1 404 Not Found"));
+}
+
+#[test]
+fn select_adapters() {
+	assert_eq!("1".parse(), Ok(AdapterSelection::Index(1)));
+	assert_eq!("RTX 3080".parse(), Ok(AdapterSelection::Name("RTX 3080".to_string())));
+
+	// selections no adapter satisfies fail with a device error
+	let adapters = pollster::block_on(Solver::adapters(wgpu::Backends::all())).len();
+	for selection in [AdapterSelection::Index(adapters), AdapterSelection::Name("no such adapter".to_string())] {
+		let error = pollster::block_on(Solver::with_adapters(wgpu::Backends::all(), &[selection])).err().unwrap();
+		assert!(matches!(error, Error::Device(_)), "{}", error);
+	}
+
+	// a usable adapter selected twice, by index or by name, is rejected
+	if adapters > 0 && pollster::block_on(Solver::with_adapters(wgpu::Backends::all(), &[AdapterSelection::Index(0)])).is_ok() {
+		let name = pollster::block_on(Solver::adapters(wgpu::Backends::all()))[0].get_info().name;
+		let error = pollster::block_on(Solver::with_adapters(wgpu::Backends::all(), &[AdapterSelection::Index(0), AdapterSelection::Name(name)]))
+			.err()
+			.unwrap();
+		assert!(matches!(error, Error::Config { field: "Adapter", .. }), "{}", error);
+	}
+}
+
+#[test]
+fn distribute_job_over_localhost() {
+	use std::io::{Read, Write};
+
+	let stencil = format!("{} {{about,above,absent}}", ["abandon"; 11].join(" "));
+	let target = targets::Target::Bitcoin(targets::parse_address("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", bitcoin::Network::Bitcoin).unwrap());
+	let targets = || targets::Targets::new([target.clone()], bitcoin::Network::Bitcoin);
+
+	let job = Job::builder([stencil.as_str()]).targets(targets().unwrap()).build().unwrap();
+	let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap().to_string();
+
+	// serving requires a token
+	assert!(matches!(
+		distributed::Coordinator::new(job.clone()).serve(listener.try_clone().unwrap(), |_| ()),
+		Err(Error::Config { field: "Token", .. })
+	));
+
+	let coordinator = distributed::Coordinator::new(job).token("secret").lease_timeout(std::time::Duration::from_millis(300));
+
+	let send = |token: &str, request: &str| {
+		let mut stream = std::net::TcpStream::connect(&address).unwrap();
+		stream.write_all(format!("auth {}\n{}\n", token, request).as_bytes()).unwrap();
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	};
+	let request = |request: &str| send("secret", request);
+
+	let mut matches = Vec::new();
+	let mut progress = Vec::new();
+
+	std::thread::scope(|scope| {
+		let server = scope.spawn(|| {
+			coordinator
+				.serve(listener, |event| match event {
+					Event::Match(found) => matches.push(found),
+					Event::Progress(update) => progress.push(update),
+				})
+				.unwrap()
+		});
+
+		// the job, passphrase included, is only described to connections presenting the token
+		assert_eq!(send("secre", "job").trim(), "unauthorized");
+		assert!(distributed::Worker::new(address.as_str(), "stranger")
+			.token("guess")
+			.job(|network| targets::Targets::new([target.clone()], network))
+			.is_err());
+
+		// a connection that never sends its request doesn't hold up the others
+		let stalled = std::net::TcpStream::connect(&address).unwrap();
+		let started = std::time::Instant::now();
+
+		// a worker that dies holding the only lease, its steps are handed out again once it expires
+		assert_eq!(request("lease dead").trim(), "lease 1 0 3 75");
+		assert!(request("lease alive").starts_with("wait "));
+		assert_eq!(request("match 1 7 m/84'/0'/0'/0/0").trim(), "rejected");
+		assert!(started.elapsed() < std::time::Duration::from_secs(5), "Requests were held up by a stalled connection");
+		drop(stalled);
+
+		let mut worker = distributed::Worker::new(address.as_str(), "alive").token("secret");
+		let job = worker.job(|network| targets::Targets::new([target.clone()], network)).unwrap().build().unwrap();
+		let solver = pollster::block_on(Solver::new(Backend::Cpu)).unwrap();
+
+		let mut found = 0;
+		worker
+			.run(&solver, &job, |event| {
+				if let Event::Match(_) = event {
+					found += 1
+				}
+			})
+			.unwrap();
+
+		assert_eq!(found, 1);
+		assert_eq!(request("progress 1 3 0").trim(), "lost");
+
+		server.join().unwrap();
+	});
+
+	// coverage is reported by the worker that checked it, the match once confirmed by the coordinator
+	assert_eq!(progress.last().map(|update| (update.device, update.next, update.completed, update.steps)), Some((1, 3, 1, 1)));
+	assert_eq!(matches.len(), 1);
+	assert_eq!(matches[0].record().mnemonic, format!("{} about", ["abandon"; 11].join(" ")));
+}
+
+#[test]
+fn build_target_index() {
+	use std::io::Write;
+
+	let directory = std::env::temp_dir().join(format!("webgpu-bip39-index-{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+
+	// a compressed CSV dump with a header, an unsupported P2WSH output and a malformed line
+	let rows = [
+		"address,amount",
+		"1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA,1000",
+		"\"bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu\",2000",
+		"bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3,3000",
+		"1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabB,4000",
+		"",
+		"0x9858EfFD232B4033E47d90003D41EC34EcaEda94;5000",
+		"1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA,6000",
+	];
+
+	let input = directory.join("utxo.csv.gz");
+	let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(&input).unwrap(), flate2::Compression::default());
+	encoder.write_all(rows.join("\n").as_bytes()).unwrap();
+	encoder.finish().unwrap();
+
+	let output = directory.join("targets.idx");
+	index::build(input.to_str().unwrap(), output.to_str().unwrap(), bitcoin::Network::Bitcoin).unwrap();
+
+	// the index is detected by its header, duplicates are merged
+	let targets = targets::Targets::read(output.to_str().unwrap(), bitcoin::Network::Bitcoin).unwrap();
+	assert_eq!(targets.len(), 3);
+	assert_eq!(targets.kinds(), &[targets::AddressKind::P2pkh, targets::AddressKind::P2wpkh, targets::AddressKind::Evm]);
+
+	for address in ["1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA", "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"] {
+		assert!(targets.contains(&targets::Target::Bitcoin(targets::parse_address(address, bitcoin::Network::Bitcoin).unwrap())));
+	}
+
+	// the same public key hash as another address kind isn't a target
+	let p2pkh = targets::parse_address("1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA", bitcoin::Network::Bitcoin).unwrap();
+	let program = bitcoin::WitnessProgram::new(bitcoin::WitnessVersion::V0, &p2pkh.pubkey_hash().unwrap()[..]).unwrap();
+	let p2wpkh = bitcoin::Address::from_witness_program(program, bitcoin::KnownHrp::Mainnet);
+	assert!(!targets.contains(&targets::Target::Bitcoin(p2wpkh)));
+	assert_eq!(targets.fingerprints().count(), 3);
+	assert!(targets.fingerprints().any(|fingerprint| fingerprint[..] == p2pkh.pubkey_hash().unwrap()[..]));
+
+	assert!(targets::Targets::load(output.to_str().unwrap(), bitcoin::Network::Testnet).is_err());
+	assert!(targets::Targets::load(input.to_str().unwrap(), bitcoin::Network::Bitcoin).is_err());
+
+	// inputs larger than a chunk are sorted in chunks and merged into the same index, without leaving chunks behind
+	let chunked = directory.join("chunked.idx");
+	index::build_in_chunks(input.to_str().unwrap(), chunked.to_str().unwrap(), bitcoin::Network::Bitcoin, 2).unwrap();
+	assert_eq!(std::fs::read(&chunked).unwrap(), std::fs::read(&output).unwrap());
+	assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 3);
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn format_match_records() {
+	let record = output::Record {
+		mnemonic: format!("{} about", ["abandon"; 11].join(" ")),
+		entropy: "00".repeat(16),
+		passphrase: "say \"hi\", then\tleave".to_string(),
+		master_extended_key: "xprv9s21ZrQH143K3GJpoapnV8SFfukcVBSfeCficPSGfubmSFDxo1kuHnLisriDvSnRRuL2Qrg5ggqHKNVpxR86QEC8w35uxmGoggxtQTPvfUu".to_string(),
+		path: "m/84'/0'/0'/0/0".to_string(),
+		address_type: targets::AddressKind::P2wpkh.name().to_string(),
+		address: "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu".to_string(),
+		label: String::new(),
+		candidate: 3,
+		range: (0, 2048),
+	};
+
+	let jsonl = record.format(output::Format::Jsonl);
+	assert!(jsonl.starts_with(&format!("{{\"mnemonic\":\"{}\",\"entropy\":\"{}\"", record.mnemonic, record.entropy)));
+	assert!(jsonl.contains(r#""passphrase":"say \"hi\", then\tleave""#));
+	assert!(jsonl.ends_with("\"label\":\"\",\"candidate\":3,\"range\":\"0/2048\"}\n"));
+
+	let csv = record.format(output::Format::Csv);
+	assert_eq!(output::Record::header(output::Format::Csv).unwrap().trim_end().split(',').count(), output::Record::FIELDS.len());
+	assert!(csv.contains(",\"say \"\"hi\"\", then\tleave\","));
+	assert!(csv.ends_with(",p2wpkh,bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu,,3,0/2048\n"));
+
+	// text matches the format of earlier versions
+	assert!(output::Record::header(output::Format::Text).is_none());
+	assert!(record.format(output::Format::Text).starts_with("Mnemonic = \"abandon abandon"));
+
+	// labels follow addresses in an address file
+	let (target, label) = index::parse_line("\"1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA\", cold storage ", bitcoin::Network::Bitcoin).unwrap().unwrap();
+	let targets = targets::Targets::new([target.clone()], bitcoin::Network::Bitcoin)
+		.unwrap()
+		.with_labels([(target.clone(), label.unwrap())])
+		.unwrap();
+	assert_eq!(targets.label(&target), Some("cold storage"));
+	assert!(index::parse_line("   ", bitcoin::Network::Bitcoin).is_none());
+}
+
+#[test]
+fn normalize_passphrase_salt() {
+	let salt = solver::types::Block::salt("ℌello Wörld").unwrap();
+	let bytes = salt.bytes[..salt.length as usize].iter().map(|&b| b as u8).collect::<Vec<_>>();
+
+	assert_eq!(std::str::from_utf8(&bytes).unwrap(), "mnemonicHello Wo\u{308}rld");
+	assert!(solver::types::Block::salt(&"x".repeat(120)).is_ok());
+	assert!(solver::types::Block::salt(&"x".repeat(121)).is_err());
+}
+
+#[test]
+fn parse_passphrase_masks() {
+	let passphrases = solver::passphrases::Passphrases::mask("?u?l?d!??").unwrap();
+
+	assert_eq!(passphrases.space(), Some(26 * 26 * 10));
+	assert_eq!(passphrases.candidate(0), "Aa0!?");
+	assert_eq!(passphrases.candidate(1), "Aa1!?");
+	assert_eq!(passphrases.candidate(10), "Ab0!?");
+	assert_eq!(passphrases.candidate(26 * 26 * 10 - 1), "Zz9!?");
+
+	assert!(solver::passphrases::Passphrases::mask("?x").is_err());
+	assert!(solver::passphrases::Passphrases::mask("abc?").is_err());
+	assert!(solver::passphrases::Passphrases::mask(&"?d".repeat(33)).is_err());
+}
+
+#[test]
+fn verify_passphrase_derivations() {
+	verify_passphrase_derivations_on(Backend::Gpu);
+}
+
+#[test]
+fn verify_cpu_passphrase_derivations() {
+	verify_passphrase_derivations_on(Backend::Cpu);
+}
+
+fn verify_passphrase_derivations_on(backend: Backend) {
+	let phrase = "setup arrange elevator foam jelly word wire either other oblige cupboard almost";
+	let config = Config {
+		stencil: phrase.split(' ').map(|s| s.to_string()).collect(),
+		range: (0, 100),
+		mask: Some("pin?d?d".to_string()),
+		..Default::default()
+	};
+
+	let passphrases = solver::passphrases::Passphrases::from_config(&config).unwrap().unwrap();
+	let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, phrase).unwrap();
+
+	// every candidate passphrase is a target
+	let paths = paths::Paths::new(&["m/44'/0'/0'/0/0"]).unwrap();
+	let (targets, expected) = targets_of((0..100).map(|index| (index, mnemonic.to_seed(passphrases.candidate(index)))), &paths);
+
+	// start monitoring thread
+	let (sender, receiver) = flume::bounded::<solver::StageComputation>(64);
+
+	let thread = std::thread::spawn(move || {
+		let (hits, derived) = collect_hits(receiver);
+
+		assert_eq!(derived, 100, "Not every candidate passphrase was derived");
+		assert_eq!(hits, expected);
+	});
+
+	solve(&config, backend, &targets, &paths, sender);
+	thread.join().unwrap();
+}
+
+#[test]
+fn validate_shaders() {
+	use wgpu::naga;
+
+	let sources = [
+		("filter_stage", solver::passes::filter::FilterPass::SOURCE),
+		("derivation_stage", solver::passes::derivation::DerivationPass::SOURCE),
+		("passphrase_stage", solver::passes::passphrase::PassphrasePass::SOURCE),
+		("public_key_stage", solver::passes::public_key::PublicKeyPass::SOURCE),
+	];
+
+	let read = |paths: &[&str]| paths.iter().fold(String::new(), |acc, nxt| acc + "\n" + &std::fs::read_to_string(nxt).unwrap());
+	let test_short256 = read(&["src/shaders/short256.wgsl", "src/tests/test_short256.wgsl"]);
+	let test_pbkdf2 = read(&["src/shaders/sha512.wgsl", "src/shaders/pbkdf2.wgsl", "src/tests/test_pbkdf2.wgsl"]);
+
+	let tests = [("test_short256", test_short256.as_str()), ("test_pbkdf2", test_pbkdf2.as_str())];
+
+	for (name, source) in sources.into_iter().chain(tests) {
+		let module = naga::front::wgsl::parse_str(source).unwrap_or_else(|err| panic!("{}: {}", name, err.emit_to_string(source)));
+
+		let mut validator = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::IMMEDIATES | naga::valid::Capabilities::SHADER_INT64);
+		validator.validate(&module).unwrap_or_else(|err| panic!("{}: {}", name, err.emit_to_string(source)));
+	}
 }
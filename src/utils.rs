@@ -1,18 +1,72 @@
 use super::*;
 
-pub(super) fn verify_config(config: &Config) {
+/// Verifies the stencil, range, passphrases and paths of a config, binding its range to the problem space and selecting its language
+pub(crate) fn verify_config(config: &mut Config) -> Result<(), Error> {
+	// select or detect the word list of the stencil
+	let language = match solver::stencil::language(config) {
+		Ok(language) => language,
+		Err(err) => return Err(Error::config("Language", err)),
+	};
+
 	// verify stencil words
-	if config.range.1 > 2u64.pow(44) || config.range.0 > config.range.1 {
-		panic!("Invalid Range: Maximum problem space is [0, 17592186044416] (2^44)");
+	let stencil = match solver::stencil::Stencil::parse(config.stencil.iter().map(|s| s.as_str()), language) {
+		Ok(stencil) => stencil,
+		Err(err) => return Err(Error::Stencil(format!("{}\n Eg: throw _ bulk opinion _ _ party exact guide _ change thought", err))),
+	};
+
+	// passphrase recovery enumerates passphrases of a complete mnemonic
+	let passphrases = match solver::passphrases::Passphrases::from_config(config) {
+		Ok(passphrases) => passphrases,
+		Err(err) => return Err(Error::config("Passphrases", err)),
+	};
+
+	let space = match passphrases {
+		Some(passphrases) => {
+			if config.passphrase.is_some() {
+				return Err(Error::config("Config", "A fixed passphrase can't be used during passphrase recovery"));
+			}
+
+			if stencil.header.unknown_count != 0 || stencil.mnemonic(0, language).is_err() {
+				return Err(Error::Stencil("Passphrase recovery requires a complete mnemonic with a valid checksum".to_string()));
+			}
+
+			passphrases.space()
+		}
+		None => stencil.space(),
+	};
+
+	// bind range to the problem space of the stencil
+	let Some(space) = space else {
+		return Err(Error::config("Config", "Problem space exceeds 2^64"));
 	};
 
-	if let Some(unknown) = config.stencil.iter().find(|w| *w != "_" && !bip39::Language::English.word_list().contains(&w.as_str())) {
-		panic!("Invalid Stencil: Contains Unknown Word {}", unknown)
+	config.range.1 = config.range.1.min(space);
+	if config.range.0 >= config.range.1 {
+		return Err(Error::config("Range", format!("Maximum problem space is [0, {})", space)));
 	};
 
-	if config.stencil.len() != 12 || !config.stencil.iter().enumerate().all(|(idx, ss)| (4..8).contains(&idx) || (ss != "_")) {
-		panic!("Invalid Stencil Pattern: Expected 4 words, 4 stars and 4 words\n Eg: throw roast bulk opinion * * * * guide female change thought");
+	// verify passphrase fits into the pbkdf2 salt
+	if let Err(err) = solver::types::Block::salt(config.passphrase.as_deref().unwrap_or_default()) {
+		return Err(Error::config("Passphrase", err));
 	};
 
-	log::debug!("Verified Stencil and Config Range");
+	// verify derivation path templates, standard paths are used if none are given
+	if let Err(err) = paths::Paths::new(&config.path) {
+		return Err(Error::config("Path", err));
+	};
+
+	// every step in flight needs a set of buffers
+	if config.buffers == Some(0) {
+		return Err(Error::config("Buffers", "At least one set of buffers is required"));
+	}
+
+	config.language = Some(language);
+	log::debug!(
+		"Verified Stencil and Config Range: Language = {}, Unknown Words = {}, Range = {:?}",
+		language,
+		stencil.header.unknown_count,
+		config.range
+	);
+
+	Ok(())
 }